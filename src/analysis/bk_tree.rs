@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+/// Levenshtein distance between two words, counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, char_a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, char_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(char_a != *char_b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+struct Node {
+    term: String,
+    children: HashMap<usize, usize>, // distance -> node index
+}
+
+/// A Burkhard-Keller tree over the index terms, used to find terms within a given edit distance.
+#[derive(Default)]
+pub struct BkTree {
+    nodes: Vec<Node>,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, term: String) {
+        let new_index = self.nodes.len();
+        if self.nodes.is_empty() {
+            self.nodes.push(Node {
+                term,
+                children: HashMap::new(),
+            });
+            return;
+        }
+        let mut index = 0;
+        loop {
+            let distance = levenshtein(&self.nodes[index].term, &term);
+            if distance == 0 {
+                return;
+            }
+            match self.nodes[index].children.get(&distance) {
+                Some(&child) => index = child,
+                None => {
+                    self.nodes[index].children.insert(distance, new_index);
+                    break;
+                }
+            }
+        }
+        self.nodes.push(Node {
+            term,
+            children: HashMap::new(),
+        });
+    }
+
+    /// Returns all terms within `max_distance` of `word` together with their distances.
+    pub fn find(&self, word: &str, max_distance: usize) -> Vec<(usize, &str)> {
+        let mut result = Vec::new();
+        if self.nodes.is_empty() {
+            return result;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = levenshtein(&node.term, word);
+            if distance <= max_distance {
+                result.push((distance, node.term.as_str()));
+            }
+            let min_child = distance.saturating_sub(max_distance);
+            let max_child = distance + max_distance;
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(child_distance, _)| (min_child..=max_child).contains(*child_distance))
+                    .map(|(_, child)| *child),
+            );
+        }
+        result.sort();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{levenshtein, BkTree};
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(0, levenshtein("деплой", "деплой"));
+        assert_eq!(1, levenshtein("деплой", "деплои"));
+        assert_eq!(2, levenshtein("деплой", "деплйо"));
+        assert_eq!(3, levenshtein("", "abc"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut tree = BkTree::new();
+        for term in ["деплой", "деплоить", "релиз", "деплои", "депо", "деплой"]
+        {
+            tree.insert(term.to_string());
+        }
+        assert_eq!(vec![(0, "деплой"), (1, "деплои")], tree.find("деплой", 1));
+        assert_eq!(
+            vec![(1, "деплои"), (1, "деплой"), (2, "депо")],
+            tree.find("деплоф", 2)
+        );
+        assert!(tree.find("абвгд", 1).is_empty());
+    }
+}
@@ -4,9 +4,10 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 #[derive(Clone)]
-pub enum TextEntity {
-    Lemmatizable(String),
-    Illemmatizable(String),
+pub struct TextEntity {
+    pub entity_type: DeserializedTextEntityType,
+    pub text: String,
+    pub href: Option<String>,
 }
 
 #[derive(Clone)]
@@ -14,6 +15,7 @@ pub struct Message {
     pub id: usize,
     pub text_entities: Vec<TextEntity>,
     pub date_unixtime: u32,
+    pub from: Option<String>,
     pub reply_to_message_id: Option<usize>,
 }
 
@@ -22,22 +24,17 @@ impl From<DeserializedMessage> for Message {
         let text_entities = value
             .text_entities
             .into_iter()
-            .map(|entity| match entity.entity_type {
-                DeserializedTextEntityType::Link
-                | DeserializedTextEntityType::CustomEmoji
-                | DeserializedTextEntityType::MentionName
-                | DeserializedTextEntityType::BotCommand
-                | DeserializedTextEntityType::Phone
-                | DeserializedTextEntityType::Mention
-                | DeserializedTextEntityType::Code
-                | DeserializedTextEntityType::Email => TextEntity::Illemmatizable(entity.text),
-                _ => TextEntity::Lemmatizable(entity.text),
+            .map(|entity| TextEntity {
+                entity_type: entity.entity_type,
+                text: entity.text,
+                href: entity.href,
             })
             .collect();
         Message {
             id: value.id as usize,
             text_entities,
             date_unixtime: value.date_unixtime,
+            from: value.from,
             reply_to_message_id: value.reply_to_message_id.map(|id| id as usize),
         }
     }
@@ -45,11 +42,58 @@ impl From<DeserializedMessage> for Message {
 
 impl From<TextEntity> for String {
     fn from(value: TextEntity) -> String {
-        match value {
-            TextEntity::Lemmatizable(text) => text,
-            TextEntity::Illemmatizable(text) => text,
+        value.text
+    }
+}
+
+impl TextEntity {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the text consists of words, as opposed to links, code, emails and the like.
+    pub fn is_lemmatizable(&self) -> bool {
+        !matches!(
+            self.entity_type,
+            DeserializedTextEntityType::Link
+                | DeserializedTextEntityType::CustomEmoji
+                | DeserializedTextEntityType::MentionName
+                | DeserializedTextEntityType::BotCommand
+                | DeserializedTextEntityType::Phone
+                | DeserializedTextEntityType::Mention
+                | DeserializedTextEntityType::Code
+                | DeserializedTextEntityType::Email
+        )
+    }
+
+    /// Where the entity links to, `text_link`s hide the target behind their text.
+    pub fn url(&self) -> Option<&str> {
+        match self.entity_type {
+            DeserializedTextEntityType::Link => Some(&self.text),
+            DeserializedTextEntityType::TextLink => self.href.as_deref(),
+            _ => None,
         }
     }
+
+    /// What a field query looks for in the entity: its text and, for a `text_link`, the
+    /// target hidden behind it, so that `link:github.com` finds "this PR".
+    pub fn field_texts(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.text.as_str()).chain(self.url().filter(|url| *url != self.text))
+    }
+
+    /// The query field that matches this entity, e.g. `code` for `code:unwrap`.
+    pub fn field(&self) -> Option<&'static str> {
+        ENTITY_FIELDS
+            .iter()
+            .find(|(_, entity_types)| entity_types.contains(&self.entity_type))
+            .map(|(field, _)| *field)
+    }
+}
+
+impl Message {
+    pub fn text(&self) -> String {
+        self.text_entities.iter().map(TextEntity::text).collect()
+    }
 }
 
 impl From<Message> for String {
@@ -112,9 +156,48 @@ enum DeserializedMessageType {
     Message,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+/// The query fields searching the entities of the given types, as in `code:unwrap`.
+/// Both the query parser and the index take the field names from here.
+pub(crate) const ENTITY_FIELDS: &[(&str, &[DeserializedTextEntityType])] = &[
+    (
+        "link",
+        &[
+            DeserializedTextEntityType::Link,
+            DeserializedTextEntityType::TextLink,
+        ],
+    ),
+    (
+        "mention",
+        &[
+            DeserializedTextEntityType::Mention,
+            DeserializedTextEntityType::MentionName,
+        ],
+    ),
+    ("hashtag", &[DeserializedTextEntityType::Hashtag]),
+    (
+        "code",
+        &[
+            DeserializedTextEntityType::Code,
+            DeserializedTextEntityType::Pre,
+        ],
+    ),
+    ("email", &[DeserializedTextEntityType::Email]),
+    ("phone", &[DeserializedTextEntityType::Phone]),
+    ("command", &[DeserializedTextEntityType::BotCommand]),
+    ("bold", &[DeserializedTextEntityType::Bold]),
+    ("italic", &[DeserializedTextEntityType::Italic]),
+    ("underline", &[DeserializedTextEntityType::Underline]),
+    (
+        "strikethrough",
+        &[DeserializedTextEntityType::Strikethrough],
+    ),
+    ("spoiler", &[DeserializedTextEntityType::Spoiler]),
+    ("quote", &[DeserializedTextEntityType::Blockquote]),
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
-enum DeserializedTextEntityType {
+pub enum DeserializedTextEntityType {
     Link,
     CustomEmoji,
     Spoiler,
@@ -140,6 +223,8 @@ struct DeserializedTextEntity {
     #[serde(rename = "type")]
     entity_type: DeserializedTextEntityType,
     text: String,
+    #[serde(default)]
+    href: Option<String>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -149,6 +234,8 @@ struct DeserializedMessage {
     pub message_type: DeserializedMessageType,
     #[serde(deserialize_with = "from_str")]
     pub date_unixtime: u32,
+    #[serde(default)]
+    pub from: Option<String>,
     pub text_entities: Vec<DeserializedTextEntity>,
     #[serde(default)]
     pub reply_to_message_id: Option<u32>,
@@ -236,22 +323,27 @@ mod tests {
             id: 3,
             message_type: DeserializedMessageType::Message,
             date_unixtime: 1590692021,
+            from: Some("User1".to_string()),
             text_entities: vec![
                 DeserializedTextEntity {
                     entity_type: DeserializedTextEntityType::Bold,
                     text: "abc".to_string(),
+                    href: None,
                 },
                 DeserializedTextEntity {
                     entity_type: DeserializedTextEntityType::Plain,
                     text: "123".to_string(),
+                    href: None,
                 },
                 DeserializedTextEntity {
                     entity_type: DeserializedTextEntityType::Link,
                     text: "https://google.com/".to_string(),
+                    href: None,
                 },
                 DeserializedTextEntity {
                     entity_type: DeserializedTextEntityType::Plain,
                     text: "".to_string(),
+                    href: None,
                 },
             ],
             reply_to_message_id: None,
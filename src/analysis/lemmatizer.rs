@@ -16,6 +16,8 @@ impl Lemmatizer {
         let mut decoder = GzDecoder::new(DICTIONARY_RAW);
         let mut data = String::new();
         decoder.read_to_string(&mut data).unwrap();
+        // the tokenizer folds "ё" into "е", so the dictionary has to match
+        let data = data.replace('ё', "е");
 
         let data = Box::leak(data.into_boxed_str());
 
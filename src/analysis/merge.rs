@@ -1,109 +1,113 @@
-use std::cmp::Ordering;
-use std::iter::Peekable;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
-pub struct MergeOr<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-{
-    left: Peekable<L>,
-    right: Peekable<R>,
+/// The position of the first element not less than `item` in a sorted list. The step is
+/// doubled until it overshoots and then the last step is searched, so finding an element
+/// close to the start is cheap even in a long list.
+fn gallop(list: &[usize], item: usize) -> usize {
+    let mut bound = 1;
+    while bound <= list.len() && list[bound - 1] < item {
+        bound *= 2;
+    }
+    let low = bound / 2;
+    let high = bound.min(list.len());
+    low + list[low..high].partition_point(|element| *element < item)
 }
 
-impl<L, R> MergeOr<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-{
-    pub(crate) fn new(left: L, right: R) -> Self {
-        MergeOr {
-            left: left.peekable(),
-            right: right.peekable(),
+/// Intersection of two sorted lists. Walks the shorter one and gallops through the longer
+/// one, so intersecting a rare word with a frequent one costs about as much as the rare one.
+pub(crate) fn intersect(left: &[usize], right: &[usize]) -> Vec<usize> {
+    let (short, long) = if left.len() <= right.len() {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    let mut result = Vec::with_capacity(short.len());
+    let mut start = 0;
+    for &item in short {
+        start += gallop(&long[start..], item);
+        if start == long.len() {
+            break;
+        }
+        if long[start] == item {
+            result.push(item);
+            start += 1;
         }
     }
+    result
 }
 
-impl<L, R> Iterator for MergeOr<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-    L::Item: Ord,
-{
-    type Item = L::Item;
-
-    fn next(&mut self) -> Option<L::Item> {
-        let which = match (self.left.peek(), self.right.peek()) {
-            (Some(l), Some(r)) => Some(l.cmp(r)),
-            (Some(_), None) => Some(Ordering::Less),
-            (None, Some(_)) => Some(Ordering::Greater),
-            (None, None) => None,
-        };
-
-        match which {
-            Some(Ordering::Less) => self.left.next(),
-            Some(Ordering::Equal) => {
-                self.left.next();
-                self.right.next()
-            }
-            Some(Ordering::Greater) => self.right.next(),
-            None => None,
+/// Elements of the sorted `left` list that are not in the sorted `right` one.
+pub(crate) fn difference(left: &[usize], right: &[usize]) -> Vec<usize> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut start = 0;
+    for &item in left {
+        start += gallop(&right[start..], item);
+        if right.get(start) != Some(&item) {
+            result.push(item);
         }
     }
+    result
 }
 
-pub struct MergeAnd<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-{
-    left: Peekable<L>,
-    right: Peekable<R>,
-}
-
-impl<L, R> MergeAnd<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-{
-    pub(crate) fn new(left: L, right: R) -> Self {
-        MergeAnd {
-            left: left.peekable(),
-            right: right.peekable(),
+/// Union of any number of sorted lists, merged all at once with a heap instead of pairwise.
+pub(crate) fn union_all<L: AsRef<[usize]>>(lists: &[L]) -> Vec<usize> {
+    if let [list] = lists {
+        return list.as_ref().to_vec();
+    }
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = lists
+        .iter()
+        .enumerate()
+        .filter_map(|(i, list)| list.as_ref().first().map(|item| Reverse((*item, i))))
+        .collect();
+    let mut positions = vec![0; lists.len()];
+    let mut result = Vec::new();
+    while let Some(Reverse((item, i))) = heap.pop() {
+        if result.last() != Some(&item) {
+            result.push(item);
+        }
+        positions[i] += 1;
+        if let Some(next) = lists[i].as_ref().get(positions[i]) {
+            heap.push(Reverse((*next, i)));
         }
     }
+    result
 }
 
-impl<L, R> Iterator for MergeAnd<L, R>
-where
-    L: Iterator<Item = R::Item>,
-    R: Iterator,
-    L::Item: Ord,
-{
-    type Item = L::Item;
+#[cfg(test)]
+mod tests {
+    use super::{difference, gallop, intersect, union_all};
 
-    fn next(&mut self) -> Option<L::Item> {
-        loop {
-            match (self.left.peek(), self.right.peek()) {
-                (Some(l), Some(r)) => match l.cmp(r) {
-                    Ordering::Less => {
-                        self.left.next();
-                    }
-                    Ordering::Equal => {
-                        self.right.next();
-                        return self.left.next();
-                    }
-                    Ordering::Greater => {
-                        self.right.next();
-                    }
-                },
-                (Some(_), None) => {
-                    self.left.next();
-                }
-                (None, Some(_)) => {
-                    self.right.next();
-                }
-                (None, None) => return None,
-            }
-        }
+    #[test]
+    fn test_gallop() {
+        let list = [1, 3, 5, 7, 9, 11, 13];
+        assert_eq!(0, gallop(&list, 0));
+        assert_eq!(0, gallop(&list, 1));
+        assert_eq!(3, gallop(&list, 6));
+        assert_eq!(6, gallop(&list, 13));
+        assert_eq!(7, gallop(&list, 14));
+        assert_eq!(0, gallop(&[], 1));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let rare = [3, 40, 99];
+        let frequent: Vec<usize> = (0..100).filter(|i| i % 3 == 0 || *i == 40).collect();
+        assert_eq!(vec![3, 40, 99], intersect(&rare, &frequent));
+        assert_eq!(vec![3, 40, 99], intersect(&frequent, &rare));
+        assert!(intersect(&[1, 2], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(vec![1, 4], difference(&[1, 2, 3, 4], &[0, 2, 3, 5]));
+        assert_eq!(vec![1, 2], difference(&[1, 2], &[]));
+    }
+
+    #[test]
+    fn test_union_all() {
+        let lists = vec![vec![1, 5, 9], vec![], vec![2, 5, 10], vec![0, 9]];
+        assert_eq!(vec![0, 1, 2, 5, 9, 10], union_all(&lists));
+        assert!(union_all::<Vec<usize>>(&[]).is_empty());
     }
 }
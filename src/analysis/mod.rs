@@ -1,10 +1,23 @@
+mod bk_tree;
 mod deserialization;
 mod lemmatizer;
 mod merge;
+mod planner;
+mod postings;
 mod query;
 mod searcher;
+mod segmentation;
+mod synonyms;
+mod term_dictionary;
 mod thread_dsu;
+mod tokenizer;
+mod url;
 mod utils;
 
 pub use lemmatizer::Lemmatizer;
-pub use searcher::{MessageResult, Searcher, Text, ThreadSearchResult};
+pub use query::QueryError;
+pub use searcher::{
+    Completion, LinkResult, MessageTree, QueryWords, Searcher, Text, ThreadPage, ThreadSearchResult,
+};
+pub use segmentation::Segmentation;
+pub use tokenizer::Tokenizer;
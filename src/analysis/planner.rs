@@ -0,0 +1,80 @@
+use crate::analysis::query::SearchQuery;
+
+/// A query with the nested AND and OR chains flattened, so that all operands of a chain
+/// are known at once and can be evaluated in the cheapest order.
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryPlan {
+    /// A query looked up in the index as a whole
+    Leaf(SearchQuery),
+    And(Vec<QueryPlan>),
+    Or(Vec<QueryPlan>),
+    Not(Box<QueryPlan>),
+}
+
+impl From<SearchQuery> for QueryPlan {
+    fn from(query: SearchQuery) -> Self {
+        match query {
+            SearchQuery::And(_) => {
+                let mut operands = Vec::new();
+                flatten(query, true, &mut operands);
+                QueryPlan::And(operands)
+            }
+            SearchQuery::Or(_) => {
+                let mut operands = Vec::new();
+                flatten(query, false, &mut operands);
+                QueryPlan::Or(operands)
+            }
+            SearchQuery::Not(query) => match QueryPlan::from(*query) {
+                QueryPlan::Not(plan) => *plan,
+                plan => QueryPlan::Not(Box::new(plan)),
+            },
+            query => QueryPlan::Leaf(query),
+        }
+    }
+}
+
+fn flatten(query: SearchQuery, is_and: bool, operands: &mut Vec<QueryPlan>) {
+    match query {
+        SearchQuery::And((query_left, query_right)) if is_and => {
+            flatten(*query_left, is_and, operands);
+            flatten(*query_right, is_and, operands);
+        }
+        SearchQuery::Or((query_left, query_right)) if !is_and => {
+            flatten(*query_left, is_and, operands);
+            flatten(*query_right, is_and, operands);
+        }
+        query => operands.push(QueryPlan::from(query)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryPlan;
+    use crate::analysis::query::{Lexer, Parser, SearchQuery};
+
+    fn plan(input: &str) -> QueryPlan {
+        QueryPlan::from(Parser::new(Lexer::new(input)).unwrap().parse().unwrap())
+    }
+
+    fn word(value: &str) -> QueryPlan {
+        QueryPlan::Leaf(SearchQuery::Word(value.to_string()))
+    }
+
+    #[test]
+    fn test_flatten() {
+        let expected = QueryPlan::Or(vec![
+            QueryPlan::And(vec![word("a1"), word("b1"), word("c1")]),
+            word("d1"),
+            QueryPlan::And(vec![
+                QueryPlan::Or(vec![word("e1"), word("f1"), word("g1")]),
+                QueryPlan::Not(Box::new(word("h1"))),
+            ]),
+        ]);
+        assert_eq!(expected, plan("a1 & (b1 & c1) | d1 | (e1 | (f1 | g1)) -h1"));
+    }
+
+    #[test]
+    fn test_double_negation() {
+        assert_eq!(word("a1"), plan("NOT -a1"));
+    }
+}
@@ -0,0 +1,264 @@
+use std::iter::FromIterator;
+
+/// How many ids share a skip entry, at most that many are decoded to find an id.
+const BLOCK_SIZE: usize = 64;
+
+/// A sorted list of ids stored as varint-encoded deltas, mostly a byte per id instead of
+/// eight. The first id of every block is kept apart together with the offset of the block,
+/// so that a search can skip straight to the block that may contain an id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostingList {
+    bytes: Vec<u8>,
+    blocks: Vec<(usize, usize)>, // (first id, byte offset) of every block
+    len: usize,
+    last: usize,
+}
+
+pub static EMPTY_POSTING_LIST: PostingList = PostingList::new();
+
+impl PostingList {
+    pub const fn new() -> Self {
+        PostingList {
+            bytes: Vec::new(),
+            blocks: Vec::new(),
+            len: 0,
+            last: 0,
+        }
+    }
+
+    /// Appends an id, which has to be greater than all the ids already in the list.
+    pub fn push(&mut self, id: usize) {
+        debug_assert!(self.len == 0 || id > self.last);
+        if self.len.is_multiple_of(BLOCK_SIZE) {
+            self.blocks.push((id, self.bytes.len()));
+        } else {
+            write_varint(&mut self.bytes, id - self.last);
+        }
+        self.last = id;
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> Cursor<'_> {
+        Cursor {
+            list: self,
+            position: 0,
+            offset: 0,
+            current: 0,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+
+    /// Ids present both in the list and in the sorted `ids`, which are expected to be the
+    /// shorter of the two: only the blocks that may contain one of them are decoded.
+    pub fn intersect(&self, ids: &[usize]) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut cursor = self.iter();
+        let mut candidate = None;
+        for &id in ids {
+            if !matches!(candidate, Some(candidate) if candidate >= id) {
+                candidate = cursor.next_at_least(id);
+            }
+            match candidate {
+                Some(candidate) if candidate == id => result.push(id),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        result
+    }
+}
+
+impl FromIterator<usize> for PostingList {
+    fn from_iter<I: IntoIterator<Item = usize>>(ids: I) -> Self {
+        let mut list = PostingList::new();
+        for id in ids {
+            list.push(id);
+        }
+        list
+    }
+}
+
+pub struct Cursor<'a> {
+    list: &'a PostingList,
+    position: usize, // index of the next id
+    offset: usize,   // byte offset of the next delta
+    current: usize,
+}
+
+impl Cursor<'_> {
+    /// Consumes the ids up to the first one not less than `id` and returns it.
+    pub fn next_at_least(&mut self, id: usize) -> Option<usize> {
+        let block = self.list.blocks.partition_point(|(first, _)| *first <= id);
+        if block > 0 && (block - 1) * BLOCK_SIZE > self.position {
+            self.position = (block - 1) * BLOCK_SIZE;
+        }
+        self.find(|next| *next >= id)
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.position == self.list.len {
+            return None;
+        }
+        if self.position.is_multiple_of(BLOCK_SIZE) {
+            let (first, offset) = self.list.blocks[self.position / BLOCK_SIZE];
+            self.current = first;
+            self.offset = offset;
+        } else {
+            let (delta, length) = read_varint(&self.list.bytes[self.offset..]);
+            self.current += delta;
+            self.offset += length;
+        }
+        self.position += 1;
+        Some(self.current)
+    }
+}
+
+/// The (message id, position) pairs of a lemma in order, stored as varint deltas like
+/// `PostingList`: the delta of the message id, then the delta of the position from the
+/// previous one in the same message, or the position itself in a new message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionList {
+    bytes: Vec<u8>,
+    last: (usize, usize),
+}
+
+impl PositionList {
+    /// Appends a pair, which can't come before the pairs already in the list.
+    pub fn push(&mut self, message_id: usize, position: usize) {
+        debug_assert!((message_id, position) >= self.last);
+        write_varint(&mut self.bytes, message_id - self.last.0);
+        if message_id == self.last.0 {
+            write_varint(&mut self.bytes, position - self.last.1);
+        } else {
+            write_varint(&mut self.bytes, position);
+        }
+        self.last = (message_id, position);
+    }
+
+    pub fn iter(&self) -> PositionCursor<'_> {
+        PositionCursor {
+            bytes: &self.bytes,
+            current: (0, 0),
+        }
+    }
+}
+
+pub struct PositionCursor<'a> {
+    bytes: &'a [u8],
+    current: (usize, usize),
+}
+
+impl Iterator for PositionCursor<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (message_delta, length) = read_varint(self.bytes);
+        let (position, position_length) = read_varint(&self.bytes[length..]);
+        self.bytes = &self.bytes[length + position_length..];
+        self.current = if message_delta == 0 {
+            (self.current.0, self.current.1 + position)
+        } else {
+            (self.current.0 + message_delta, position)
+        };
+        Some(self.current)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Returns the value and the number of bytes it took.
+fn read_varint(bytes: &[u8]) -> (usize, usize) {
+    let mut value = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+    (value, bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_varint, write_varint, PositionList, PostingList};
+
+    #[test]
+    fn test_varint() {
+        let mut bytes = Vec::new();
+        for (value, length) in [(0, 1), (127, 1), (128, 2), (300, 2), (1 << 40, 6)] {
+            bytes.clear();
+            write_varint(&mut bytes, value);
+            assert_eq!(length, bytes.len());
+            assert_eq!((value, length), read_varint(&bytes));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ids: Vec<usize> = (0..1000).map(|i| i * i + 7).collect();
+        let list: PostingList = ids.iter().copied().collect();
+        assert_eq!(ids.len(), list.len());
+        assert_eq!(ids, list.to_vec());
+        assert!(PostingList::new().to_vec().is_empty());
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        let pairs = vec![
+            (0, 0),
+            (0, 3),
+            (0, 3),
+            (5, 1),
+            (5, 200),
+            (300, 0),
+            (1 << 20, 7),
+        ];
+        let mut list = PositionList::default();
+        for (message_id, position) in &pairs {
+            list.push(*message_id, *position);
+        }
+        assert_eq!(pairs, list.iter().collect::<Vec<_>>());
+        assert_eq!(None, PositionList::default().iter().next());
+    }
+
+    #[test]
+    fn test_intersect() {
+        let list: PostingList = (0..10_000).filter(|i| i % 3 == 0).collect();
+        assert_eq!(
+            vec![0, 3000, 9999],
+            list.intersect(&[0, 1, 2999, 3000, 9998, 9999, 10_002])
+        );
+        assert!(list.intersect(&[]).is_empty());
+        assert!(PostingList::new().intersect(&[1, 2]).is_empty());
+    }
+
+    #[test]
+    fn test_next_at_least() {
+        let list: PostingList = (0..1000).map(|i| i * 2).collect();
+        let mut cursor = list.iter();
+        assert_eq!(Some(500), cursor.next_at_least(499));
+        assert_eq!(Some(502), cursor.next());
+        assert_eq!(Some(1000), cursor.next_at_least(1000));
+        assert_eq!(None, cursor.next_at_least(5000));
+    }
+}
@@ -1,10 +1,85 @@
-use anyhow::{anyhow, Result};
+use crate::analysis::deserialization::ENTITY_FIELDS;
+use crate::analysis::tokenizer::{is_joiner, is_word_char};
+use std::fmt;
+use std::ops::Range;
+
+/// A query that could not be parsed or evaluated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// Character range of the offending part of the query
+    pub span: Option<Range<usize>>,
+    /// What would have been accepted instead
+    pub expected: Vec<&'static str>,
+}
+
+impl QueryError {
+    pub(crate) fn new(message: String) -> Self {
+        QueryError {
+            message,
+            span: None,
+            expected: Vec::new(),
+        }
+    }
+
+    fn at(message: String, span: Range<usize>, expected: &[&'static str]) -> Self {
+        QueryError {
+            message,
+            span: Some(span),
+            expected: expected.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected {}", self.expected.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+type Result<T> = std::result::Result<T, QueryError>;
+
+const OPERAND: &[&str] = &["a word", "(", "NOT"];
+
+/// Fields that don't stand for a kind of text entity: the sender and the date of a message,
+/// and the address of a link.
+const MESSAGE_FIELDS: &[&str] = &["from", "date"];
+const LINK_FIELDS: &[&str] = &["url", "domain"];
+
+/// Fields restricting a search to a sender, a date, or one kind of text entity,
+/// as in `code:unwrap`.
+pub(crate) fn field_names() -> Vec<&'static str> {
+    let entity_fields = ENTITY_FIELDS.iter().map(|(field, _)| *field);
+    MESSAGE_FIELDS
+        .iter()
+        .copied()
+        .chain(entity_fields)
+        .chain(LINK_FIELDS.iter().copied())
+        .collect()
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum SearchQuery {
     Word(String),
+    /// A word already brought to its lemma, only synonym expansion produces it
+    Lemma(String),
+    Fuzzy(String),
+    Wildcard(String),
+    Exact(String),
+    Regex(String),
+    /// Text inside of an entity of the given field, any such entity if the text is empty
+    Field((String, String)),
     And((Box<SearchQuery>, Box<SearchQuery>)),
     Or((Box<SearchQuery>, Box<SearchQuery>)),
+    Not(Box<SearchQuery>),
+    /// Both words within the given number of words of each other in the same message
+    Near((Box<SearchQuery>, Box<SearchQuery>, usize)),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -13,18 +88,35 @@ pub(crate) enum Token {
     RParen,
     And,
     Or,
+    Not,
+    Near(usize),
     Word(String),
+    Fuzzy(String),
+    Wildcard(String),
+    Exact(String),
+    Regex(String),
+    Field((String, String)),
     Eof,
 }
 
 pub(crate) struct Lexer<'a> {
     input: &'a str,
     pos: usize,
+    token_start: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub(crate) fn new(input: &'a str) -> Self {
-        Lexer { input, pos: 0 }
+        Lexer {
+            input,
+            pos: 0,
+            token_start: 0,
+        }
+    }
+
+    /// Character range of the last read token.
+    pub(crate) fn span(&self) -> Range<usize> {
+        self.token_start..self.pos
     }
 
     pub(crate) fn next_token(&mut self) -> Result<Token> {
@@ -36,12 +128,78 @@ impl<'a> Lexer<'a> {
                 continue;
             }
 
-            if current_char.is_alphanumeric() {
-                return Ok(Token::Word(self.word()));
+            self.token_start = self.pos;
+
+            if current_char.is_alphanumeric() || current_char == '*' {
+                let word = self.word();
+                if word == "NEAR" && self.current_char() == '/' {
+                    self.pos += 1;
+                    return Ok(Token::Near(self.number()?));
+                }
+                if word == "NOT" {
+                    return Ok(Token::Not);
+                }
+                if self.current_char() == ':' {
+                    let fields = field_names();
+                    if !fields.contains(&word.as_str()) {
+                        return Err(QueryError::at(
+                            format!("Unknown field {}", word),
+                            self.token_start..self.pos,
+                            &fields,
+                        ));
+                    }
+                    self.pos += 1;
+                    return Ok(Token::Field((word, self.field_value()?)));
+                }
+                if word.contains('*') {
+                    return Ok(Token::Wildcard(word));
+                }
+                if self.current_char() == '~' {
+                    self.pos += 1;
+                    return Ok(Token::Fuzzy(word));
+                }
+                return Ok(Token::Word(word));
+            }
+
+            if current_char == '=' {
+                self.pos += 1;
+                if !self.current_char().is_alphanumeric() {
+                    return Err(QueryError::at(
+                        "Nothing to match exactly".to_string(),
+                        self.token_start..self.pos,
+                        &["a word after ="],
+                    ));
+                }
+                return Ok(Token::Exact(self.word()));
+            }
+
+            if current_char == '\'' {
+                self.pos += 1;
+                if !self.current_char().is_alphanumeric() {
+                    return Err(QueryError::at(
+                        "Nothing to match exactly".to_string(),
+                        self.token_start..self.pos,
+                        &["a word after '"],
+                    ));
+                }
+                let word = self.word();
+                if self.current_char() != '\'' {
+                    return Err(QueryError::at(
+                        "Unclosed quote".to_string(),
+                        self.token_start..self.pos,
+                        &["'"],
+                    ));
+                }
+                self.pos += 1;
+                return Ok(Token::Exact(word));
+            }
+
+            if current_char == '/' {
+                return Ok(Token::Regex(self.regex()?));
             }
 
             return match current_char {
-                '|' | '/' => {
+                '|' => {
                     self.pos += 1;
                     Ok(Token::Or)
                 }
@@ -49,6 +207,10 @@ impl<'a> Lexer<'a> {
                     self.pos += 1;
                     Ok(Token::And)
                 }
+                '!' | '-' => {
+                    self.pos += 1;
+                    Ok(Token::Not)
+                }
                 '(' => {
                     self.pos += 1;
                     Ok(Token::LParen)
@@ -57,9 +219,14 @@ impl<'a> Lexer<'a> {
                     self.pos += 1;
                     Ok(Token::RParen)
                 }
-                _ => Err(anyhow!("Unexpected character: {}", current_char)),
+                _ => Err(QueryError::at(
+                    format!("Unexpected character {}", current_char),
+                    self.pos..self.pos + 1,
+                    &["a word", "an operator"],
+                )),
             };
         }
+        self.token_start = self.pos;
         Ok(Token::Eof)
     }
 
@@ -67,15 +234,107 @@ impl<'a> Lexer<'a> {
         self.input.chars().nth(self.pos).unwrap_or('\0')
     }
 
+    fn peek_char(&self) -> char {
+        self.input.chars().nth(self.pos + 1).unwrap_or('\0')
+    }
+
     fn skip_whitespace(&mut self) {
         while self.pos < self.input.len() && self.current_char().is_whitespace() {
             self.pos += 1;
         }
     }
 
+    /// Reads a regex between two slashes, `\/` stands for a slash inside of it.
+    fn regex(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut regex = String::new();
+        loop {
+            if self.pos >= self.input.chars().count() {
+                return Err(QueryError::at(
+                    "Unclosed regex".to_string(),
+                    self.token_start..self.pos,
+                    &["/"],
+                ));
+            }
+            match self.current_char() {
+                '/' => {
+                    self.pos += 1;
+                    return Ok(regex);
+                }
+                '\\' if self.peek_char() == '/' => {
+                    regex.push('/');
+                    self.pos += 2;
+                }
+                c => {
+                    regex.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    /// Reads everything up to a space or a closing parenthesis, so that values like
+    /// `github.com/rust-lang` or `#release` are taken as is. Values with spaces are
+    /// quoted: `from:"Ivan Petrov"`.
+    fn field_value(&mut self) -> Result<String> {
+        let mut value = String::new();
+        if self.current_char() == '"' {
+            self.pos += 1;
+            while self.current_char() != '"' {
+                if self.pos >= self.input.chars().count() {
+                    return Err(QueryError::at(
+                        "Unclosed quote".to_string(),
+                        self.token_start..self.pos,
+                        &["\""],
+                    ));
+                }
+                value.push(self.current_char());
+                self.pos += 1;
+            }
+            self.pos += 1;
+            return Ok(value);
+        }
+        while self.pos < self.input.chars().count() {
+            let current_char = self.current_char();
+            if current_char.is_whitespace() || current_char == ')' {
+                break;
+            }
+            value.push(current_char);
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let start_pos = self.pos;
+        while self.current_char().is_ascii_digit() {
+            self.pos += 1;
+        }
+        self.input
+            .chars()
+            .skip(start_pos)
+            .take(self.pos - start_pos)
+            .collect::<String>()
+            .parse()
+            .map_err(|_| {
+                QueryError::at(
+                    "Missing distance".to_string(),
+                    self.token_start..self.pos,
+                    &["a number after NEAR/"],
+                )
+            })
+    }
+
     fn word(&mut self) -> String {
         let start_pos = self.pos;
-        while self.pos < self.input.len() && self.current_char().is_alphanumeric() {
+        loop {
+            let current_char = self.current_char();
+            let continues_word = is_word_char(current_char)
+                || current_char == '*'
+                || (is_joiner(current_char) && self.peek_char().is_alphanumeric());
+            if self.pos >= self.input.len() || !continues_word {
+                break;
+            }
             self.pos += 1;
         }
         String::from(
@@ -92,6 +351,7 @@ impl<'a> Lexer<'a> {
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Token,
+    current_span: Range<usize>,
 }
 
 impl<'a> Parser<'a> {
@@ -99,65 +359,157 @@ impl<'a> Parser<'a> {
         let mut parser = Parser {
             lexer,
             current_token: Token::Eof,
+            current_span: 0..0,
         };
-        parser.current_token = parser.lexer.next_token()?;
+        parser.advance()?;
         Ok(parser)
     }
 
     pub fn parse(&mut self) -> Result<SearchQuery> {
-        self.parse_expression()
+        let result = self.parse_expression()?;
+        if self.current_token != Token::Eof {
+            return Err(self.unexpected(&["an operator", "the end of the query"]));
+        }
+        Ok(result)
     }
 
+    // Precedence from the loosest to the tightest: OR, AND, NEAR, NOT.
     fn parse_expression(&mut self) -> Result<SearchQuery> {
-        self.parse_binary_operation()
+        let mut result = self.parse_and()?;
+
+        while self.current_token == Token::Or {
+            self.eat(Token::Or)?;
+            let rhs = self.parse_and()?;
+            result = SearchQuery::Or((Box::new(result), Box::new(rhs)));
+        }
+
+        Ok(result)
     }
 
-    fn parse_binary_operation(&mut self) -> Result<SearchQuery> {
-        let mut result = self.parse_primary()?;
+    fn parse_and(&mut self) -> Result<SearchQuery> {
+        let mut result = self.parse_near()?;
 
-        while matches!(self.current_token, Token::Or | Token::And) {
-            let op = self.current_token.clone();
-            self.eat(op.clone())?;
-            let rhs = self.parse_primary()?;
-            result = match op {
-                Token::Or => SearchQuery::Or((Box::new(result), Box::new(rhs))),
-                Token::And => SearchQuery::And((Box::new(result), Box::new(rhs))),
-                _ => unreachable!(),
-            };
+        loop {
+            if self.current_token == Token::And {
+                self.eat(Token::And)?;
+            } else if !self.starts_operand() {
+                break;
+            }
+            // operands next to each other without an operator are joined with AND
+            let rhs = self.parse_near()?;
+            result = SearchQuery::And((Box::new(result), Box::new(rhs)));
+        }
+
+        Ok(result)
+    }
+
+    fn parse_near(&mut self) -> Result<SearchQuery> {
+        let mut result = self.parse_not()?;
+
+        while let Token::Near(distance) = self.current_token {
+            self.eat(Token::Near(distance))?;
+            let rhs = self.parse_not()?;
+            result = SearchQuery::Near((Box::new(result), Box::new(rhs), distance));
         }
 
         Ok(result)
     }
 
+    fn parse_not(&mut self) -> Result<SearchQuery> {
+        if self.current_token == Token::Not {
+            self.eat(Token::Not)?;
+            Ok(SearchQuery::Not(Box::new(self.parse_not()?)))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn starts_operand(&self) -> bool {
+        matches!(
+            self.current_token,
+            Token::Word(_)
+                | Token::Fuzzy(_)
+                | Token::Wildcard(_)
+                | Token::Exact(_)
+                | Token::Regex(_)
+                | Token::Field(_)
+                | Token::Not
+                | Token::LParen
+        )
+    }
+
     fn parse_primary(&mut self) -> Result<SearchQuery> {
         match self.current_token.clone() {
             Token::Word(value) => {
                 self.eat(Token::Word(value.clone()))?;
                 Ok(SearchQuery::Word(value))
             }
+            Token::Fuzzy(value) => {
+                self.eat(Token::Fuzzy(value.clone()))?;
+                Ok(SearchQuery::Fuzzy(value))
+            }
+            Token::Wildcard(value) => {
+                self.eat(Token::Wildcard(value.clone()))?;
+                Ok(SearchQuery::Wildcard(value))
+            }
+            Token::Exact(value) => {
+                self.eat(Token::Exact(value.clone()))?;
+                Ok(SearchQuery::Exact(value))
+            }
+            Token::Regex(value) => {
+                if let Err(error) = regex::Regex::new(&value) {
+                    return Err(QueryError::at(
+                        format!("Invalid regex: {}", error),
+                        self.current_span.clone(),
+                        &[],
+                    ));
+                }
+                self.eat(Token::Regex(value.clone()))?;
+                Ok(SearchQuery::Regex(value))
+            }
+            Token::Field(value) => {
+                self.eat(Token::Field(value.clone()))?;
+                Ok(SearchQuery::Field(value))
+            }
             Token::LParen => {
                 self.eat(Token::LParen)?;
                 let result = self.parse_expression()?;
                 self.eat(Token::RParen)?;
                 Ok(result)
             }
-            _ => Err(anyhow!("Unexpected token: {:?}", self.current_token)),
+            _ => Err(self.unexpected(OPERAND)),
         }
     }
 
+    fn advance(&mut self) -> Result<()> {
+        self.current_token = self.lexer.next_token()?;
+        self.current_span = self.lexer.span();
+        Ok(())
+    }
+
     fn eat(&mut self, token: Token) -> Result<()> {
         if self.current_token == token {
-            self.current_token = self.lexer.next_token()?;
-            Ok(())
+            self.advance()
+        } else if token == Token::RParen {
+            Err(self.unexpected(&[")"]))
         } else {
-            Err(anyhow!("Unexpected token: {:?}", self.current_token))
+            Err(self.unexpected(OPERAND))
         }
     }
+
+    fn unexpected(&self, expected: &[&'static str]) -> QueryError {
+        let message = match &self.current_token {
+            Token::Eof => "Unexpected end of the query".to_string(),
+            Token::RParen => "Unmatched )".to_string(),
+            _ => "Unexpected operator".to_string(),
+        };
+        QueryError::at(message, self.current_span.clone(), expected)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Lexer, Parser, SearchQuery};
+    use super::{field_names, Lexer, Parser, QueryError, SearchQuery};
 
     #[test]
     fn test_basic() {
@@ -237,4 +589,254 @@ mod tests {
         ));
         assert_eq!(expected, output);
     }
+
+    #[test]
+    fn test_hyphenated_word() {
+        let input = String::from("кто-то & don't");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::And((
+            Box::new(SearchQuery::Word("кто-то".to_string())),
+            Box::new(SearchQuery::Word("don't".to_string())),
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let input = String::from("деплой~ | релиз");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::Or((
+            Box::new(SearchQuery::Fuzzy("деплой".to_string())),
+            Box::new(SearchQuery::Word("релиз".to_string())),
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let input = String::from("deplo* & *config*");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::And((
+            Box::new(SearchQuery::Wildcard("deplo*".to_string())),
+            Box::new(SearchQuery::Wildcard("*config*".to_string())),
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_exact() {
+        let input = String::from("=слова | 'don't'");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::Or((
+            Box::new(SearchQuery::Exact("слова".to_string())),
+            Box::new(SearchQuery::Exact("don't".to_string())),
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_unclosed_quote() {
+        let input = String::from("'слова");
+        let lexer = Lexer::new(&input);
+        assert!(Parser::new(lexer).is_err());
+    }
+
+    #[test]
+    fn test_regex() {
+        let input = String::from("/JIRA-\\d+/ | (/a\\/b/ & word)");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::Or((
+            Box::new(SearchQuery::Regex("JIRA-\\d+".to_string())),
+            Box::new(SearchQuery::And((
+                Box::new(SearchQuery::Regex("a/b".to_string())),
+                Box::new(SearchQuery::Word("word".to_string())),
+            ))),
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_unclosed_regex() {
+        let input = String::from("word | /abc");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_near() {
+        let input = String::from("деплой NEAR/5 (прод | стейдж)");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        let output = parser.parse().unwrap();
+        let expected = SearchQuery::Near((
+            Box::new(SearchQuery::Word("деплой".to_string())),
+            Box::new(SearchQuery::Or((
+                Box::new(SearchQuery::Word("прод".to_string())),
+                Box::new(SearchQuery::Word("стейдж".to_string())),
+            ))),
+            5,
+        ));
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_near_without_distance() {
+        let input = String::from("a NEAR/ b");
+        let lexer = Lexer::new(&input);
+        let mut parser = Parser::new(lexer).unwrap();
+        assert!(parser.parse().is_err());
+    }
+
+    fn parse(input: &str) -> Result<SearchQuery, QueryError> {
+        Parser::new(Lexer::new(input))?.parse()
+    }
+
+    fn word(value: &str) -> Box<SearchQuery> {
+        Box::new(SearchQuery::Word(value.to_string()))
+    }
+
+    #[test]
+    fn test_and_binds_tighter_than_or() {
+        let expected = SearchQuery::Or((
+            word("a1"),
+            Box::new(SearchQuery::And((word("b1"), word("c1")))),
+        ));
+        assert_eq!(expected, parse("a1 | b1 & c1").unwrap());
+        let expected = SearchQuery::Or((
+            Box::new(SearchQuery::And((word("a1"), word("b1")))),
+            word("c1"),
+        ));
+        assert_eq!(expected, parse("a1 & b1 | c1").unwrap());
+    }
+
+    #[test]
+    fn test_implicit_and() {
+        let expected = SearchQuery::Or((
+            Box::new(SearchQuery::And((
+                Box::new(SearchQuery::And((word("a1"), word("b1")))),
+                word("c1"),
+            ))),
+            word("d1"),
+        ));
+        assert_eq!(expected, parse("a1 b1 c1 | d1").unwrap());
+        let expected = SearchQuery::And((
+            word("a1"),
+            Box::new(SearchQuery::Or((word("b1"), word("c1")))),
+        ));
+        assert_eq!(expected, parse("a1 (b1 | c1)").unwrap());
+        let expected = SearchQuery::And((
+            word("deploy"),
+            Box::new(SearchQuery::Regex("JIRA-\\d+".to_string())),
+        ));
+        assert_eq!(expected, parse("deploy /JIRA-\\d+/").unwrap());
+    }
+
+    #[test]
+    fn test_not() {
+        let expected = SearchQuery::And((word("a1"), Box::new(SearchQuery::Not(word("b1")))));
+        assert_eq!(expected, parse("a1 -b1").unwrap());
+        assert_eq!(expected, parse("a1 & !b1").unwrap());
+        assert_eq!(expected, parse("a1 NOT b1").unwrap());
+        let expected = SearchQuery::Or((Box::new(SearchQuery::Not(word("a1"))), word("b1")));
+        assert_eq!(expected, parse("!a1 | b1").unwrap());
+        let expected = SearchQuery::Not(Box::new(SearchQuery::Or((word("a1"), word("b1")))));
+        assert_eq!(expected, parse("-(a1 | b1)").unwrap());
+    }
+
+    #[test]
+    fn test_near_binds_tighter_than_and() {
+        let expected = SearchQuery::And((
+            Box::new(SearchQuery::Near((word("a1"), word("b1"), 2))),
+            word("c1"),
+        ));
+        assert_eq!(expected, parse("a1 NEAR/2 b1 c1").unwrap());
+    }
+
+    #[test]
+    fn test_hyphen_inside_word_is_not_negation() {
+        let expected = SearchQuery::And((word("кто-то"), Box::new(SearchQuery::Not(word("то")))));
+        assert_eq!(expected, parse("кто-то -то").unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse("(a1 | b1").is_err());
+        assert!(parse("a1 )").is_err());
+        assert!(parse("a1 |").is_err());
+        assert!(parse("-").is_err());
+    }
+
+    fn field(name: &str, value: &str) -> Box<SearchQuery> {
+        Box::new(SearchQuery::Field((name.to_string(), value.to_string())))
+    }
+
+    #[test]
+    fn test_every_field_is_parsed() {
+        for name in field_names() {
+            let query = format!("{}:value", name);
+            assert_eq!(field(name, "value"), Box::new(parse(&query).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_fields() {
+        let expected = SearchQuery::And((
+            Box::new(SearchQuery::And((
+                Box::new(SearchQuery::Or((
+                    field("link", "github.com/rust-lang"),
+                    field("mention", "@bob"),
+                ))),
+                field("email", ""),
+            ))),
+            word("a1"),
+        ));
+        assert_eq!(
+            expected,
+            parse("(link:github.com/rust-lang | mention:@bob) email: a1").unwrap()
+        );
+
+        let expected = SearchQuery::And((field("from", "Ivan Petrov"), field("date", "2024-05")));
+        assert_eq!(
+            expected,
+            parse("from:\"Ivan Petrov\" date:2024-05").unwrap()
+        );
+        assert!(parse("from:\"Ivan").is_err());
+
+        let error = parse("a1 site:example.com").unwrap_err();
+        assert_eq!("Unknown field site", error.message);
+        assert_eq!(Some(3..7), error.span);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let error = parse("(a1 | b1").unwrap_err();
+        assert_eq!(Some(8..8), error.span);
+        assert_eq!(vec![")"], error.expected);
+
+        let error = parse("a1 | ) b1").unwrap_err();
+        assert_eq!("Unmatched )", error.message);
+        assert_eq!(Some(5..6), error.span);
+
+        let error = parse("слово ^").unwrap_err();
+        assert_eq!(Some(6..7), error.span);
+
+        let error = parse("a1 & /b(/ | 'c1").unwrap_err();
+        assert_eq!(Some(5..9), error.span);
+        assert!(error.message.starts_with("Invalid regex"));
+
+        let error = parse("a1 | 'c1").unwrap_err();
+        assert_eq!(Some(5..8), error.span);
+        assert_eq!(vec!["'"], error.expected);
+    }
 }
@@ -1,11 +1,22 @@
 use crate::analysis::deserialization::{deserialize_messages, Message, TextEntity};
 use crate::analysis::lemmatizer::Lemmatizer;
-use crate::analysis::merge::{MergeAnd, MergeOr};
-use crate::analysis::query::{Lexer, Parser, SearchQuery};
+use crate::analysis::merge::{difference, intersect, union_all};
+use crate::analysis::planner::QueryPlan;
+use crate::analysis::postings::{PositionList, PostingList, EMPTY_POSTING_LIST};
+use crate::analysis::query::{field_names, Lexer, Parser, QueryError, SearchQuery};
+use crate::analysis::segmentation::Segmentation;
+use crate::analysis::synonyms::Synonyms;
+use crate::analysis::term_dictionary::TermDictionary;
 use crate::analysis::thread_dsu::ThreadDSU;
+use crate::analysis::tokenizer::{is_word_char, normalize, Tokenizer};
+use crate::analysis::url::{matches_domain, parse_url, Url};
 use crate::analysis::utils;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
 use rayon::prelude::*;
+use regex::Regex;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Text {
@@ -13,12 +24,97 @@ pub enum Text {
     Highlight(String),
 }
 
+/// A part of the found threads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreadPage {
+    pub threads: Vec<ThreadSearchResult>,
+    /// Position of the first thread of the page among all found threads
+    pub offset: usize,
+    /// How many threads were found
+    pub total: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ThreadSearchResult {
     pub thread_id: u32,
     pub score: u32,
     pub title_text: String,
+    /// The first hit of the query with some context around it
+    pub snippet: Vec<Text>,
+    /// Date of the first message
     pub date_unixtime: u32,
+    /// Date of the last message
+    pub last_date_unixtime: u32,
+    pub message_count: usize,
+    /// Senders, the most active first
+    pub participants: Vec<String>,
+    /// Messages containing the words of the query
+    pub matched_count: usize,
+    /// The longest chain of replies, 0 if nobody replied
+    pub reply_depth: usize,
+}
+
+/// What is known about a thread regardless of the query.
+struct ThreadStats {
+    participants: Vec<String>,
+    reply_depth: usize,
+}
+
+impl ThreadStats {
+    fn new(message_ids: &[usize], messages: &[Message]) -> Self {
+        let mut message_counts: HashMap<&str, usize> = HashMap::new();
+        let mut depths: HashMap<usize, usize> = HashMap::new();
+        let mut reply_depth = 0;
+        // a reply always comes after the message it replies to
+        for message_id in message_ids {
+            let message = &messages[*message_id];
+            if let Some(from) = &message.from {
+                *message_counts.entry(from).or_default() += 1;
+            }
+            let depth = message
+                .reply_to_message_id
+                .and_then(|reply_to_id| depths.get(&reply_to_id))
+                .map_or(0, |depth| depth + 1);
+            reply_depth = reply_depth.max(depth);
+            depths.insert(*message_id, depth);
+        }
+        let mut participants: Vec<&str> = message_counts.keys().copied().collect();
+        participants.sort_by_key(|from| (Reverse(message_counts[from]), *from));
+        ThreadStats {
+            participants: participants.into_iter().map(String::from).collect(),
+            reply_depth,
+        }
+    }
+}
+
+/// The words to highlight in the messages found by a query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryWords {
+    /// Lemmas, matching any form of a word
+    pub lemmas: Vec<String>,
+    /// Normalized word forms that have to match exactly
+    pub exact_forms: Vec<String>,
+    /// Regexes matched against the raw message text
+    pub regexes: Vec<String>,
+    /// Fields with the normalized text their entities have to contain
+    pub fields: Vec<(String, String)>,
+}
+
+/// A link found in a message, either written out or hidden behind a `text_link`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkResult {
+    pub message_id: usize,
+    pub url: String,
+    pub domain: String,
+}
+
+/// A way to finish the last word of a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// What is shown in the list
+    pub label: String,
+    /// The whole query with the last word completed
+    pub query: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -28,53 +124,136 @@ pub struct MessageResult {
     pub reply_to_text: Option<String>,
 }
 
+/// A message of a thread together with the replies to it, in the order they were sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTree {
+    pub message: MessageResult,
+    pub replies: Vec<MessageTree>,
+}
+
 pub struct Searcher {
+    // in the order of the export, which is the order they were sent in, so their dates
+    // never decrease and are binary searched
     messages: Vec<Message>,
     threads: Vec<Vec<usize>>,
+    thread_stats: Vec<ThreadStats>,
     lemmatizer: &'static Lemmatizer,
-    thread_index: HashMap<String, Vec<usize>>,
+    tokenizer: Tokenizer,
+    thread_index: HashMap<String, PostingList>,
+    thread_norms: Vec<f32>, // lengths of the TF-IDF vectors of the threads
+    exact_index: HashMap<String, PostingList>, // word form -> thread_ids
+    positional_index: HashMap<String, PositionList>, // lemma -> (message_id, position)
+    field_index: HashMap<&'static str, Vec<(usize, String)>>, // field -> (message_id, entity text or link)
+    link_index: Vec<(usize, Url, String)>, // sorted by message_id, with the normalized url
+    domain_index: HashMap<String, Vec<usize>>, // domain -> thread_ids
+    sender_index: HashMap<String, Vec<usize>>, // sender name -> thread_ids
+    senders: Vec<String>,                  // the most active first
+    thread_by_message: Vec<usize>,
+    term_dictionary: TermDictionary,
+    form_dictionary: TermDictionary, // word forms, for suggestions in the form the user typed
+    synonyms: Synonyms,
+    utc_offset: fn(NaiveDateTime) -> i32, // seconds the local time is ahead of UTC
+}
+
+/// How many index terms a single wildcard may expand into.
+const MAX_WILDCARD_TERMS: usize = 256;
+
+/// How many completions are offered for the last word of a query.
+const MAX_COMPLETIONS: usize = 8;
+
+/// A root message with fewer words, like "?" or a lone link, doesn't make a title.
+const MIN_TITLE_WORDS: usize = 2;
+
+/// How many characters a snippet keeps on each side of the first hit.
+const SNIPPET_CONTEXT: usize = 60;
+
+/// How many related threads are offered for a thread.
+const MAX_SIMILAR_THREADS: usize = 10;
+
+/// Lemmas found in more than half of the threads tell nothing about a thread, and their
+/// posting lists are the longest to walk.
+const MIN_SIMILARITY_IDF: f32 = std::f32::consts::LN_2;
+
+/// How deep replies nest in a thread tree, deeper replies are attached next to their
+/// parent, so that long back-and-forth chains don't drift off the screen.
+const MAX_TREE_DEPTH: usize = 6;
+
+/// How many typos are tolerated in a fuzzy term, depending on its length.
+fn max_edit_distance(word: &str) -> usize {
+    if word.chars().count() <= 4 {
+        1
+    } else {
+        2
+    }
 }
 
 impl Searcher {
-    pub fn new(lemmatizer: &'static Lemmatizer, json: String) -> anyhow::Result<Searcher> {
-        let mut thread_dsu = ThreadDSU::new();
+    pub fn new(
+        lemmatizer: &'static Lemmatizer,
+        tokenizer: Tokenizer,
+        segmentation: Segmentation,
+        json: String,
+    ) -> anyhow::Result<Searcher> {
         let messages = deserialize_messages(json)?;
+        let mut thread_dsu = ThreadDSU::new(messages.len());
 
-        for message in &messages {
-            thread_dsu.make_set(message.id);
+        // replies are never chained by time, so the conversations are grouped first and
+        // the sizes the replies are split by count the whole threads. A conversation goes
+        // on over the replies sent in the middle of it.
+        let conversation = messages
+            .iter()
+            .filter(|message| message.reply_to_message_id.is_none());
+        for (previous, message) in conversation.clone().zip(conversation.skip(1)) {
+            if segmentation.continues(previous, message) {
+                thread_dsu.union_sets(previous.id, message.id);
+            }
         }
 
         for (id, message) in messages.iter().enumerate() {
             if let Some(reply_to) = message.reply_to_message_id {
-                thread_dsu.union_sets(reply_to, id); // order is important
+                let size = thread_dsu.set_size(reply_to) + thread_dsu.set_size(id);
+                if segmentation.keeps_reply(&messages[reply_to], message, size) {
+                    thread_dsu.union_sets(reply_to, id);
+                }
             }
         }
 
         let threads = thread_dsu.get_threads();
 
+        let mut thread_by_message = vec![0; messages.len()];
+        for (thread_id, message_ids) in threads.iter().enumerate() {
+            for message_id in message_ids {
+                thread_by_message[*message_id] = thread_id;
+            }
+        }
+
         let time_start = chrono::Utc::now();
 
-        let thread_id_lemmas: Vec<Vec<String>> = threads
+        let thread_id_terms: Vec<(Vec<String>, Vec<String>)> = threads
             .par_iter()
             .map(|message_ids| {
                 let mut used_words = HashSet::new();
                 let mut lemmas = Vec::new();
+                let mut used_forms = HashSet::new();
+                let mut forms = Vec::new();
                 for message_id in message_ids {
                     for text_entity in &messages[*message_id].text_entities {
-                        if let TextEntity::Lemmatizable(text) = text_entity {
-                            for word in text.split(|c: char| !c.is_alphanumeric()) {
-                                if word.len() > 3 {
-                                    let lemma = lemmatizer.lemmatize(word).to_string();
-                                    if !used_words.contains(&lemma) {
-                                        used_words.insert(lemma.clone());
-                                        lemmas.push(lemma);
-                                    }
+                        if text_entity.is_lemmatizable() {
+                            for word in tokenizer.words(&text_entity.text) {
+                                let lemma = lemmatizer.lemmatize(&word).to_string();
+                                if !used_words.contains(&lemma) {
+                                    used_words.insert(lemma.clone());
+                                    lemmas.push(lemma);
+                                }
+                                if !used_forms.contains(&word) {
+                                    used_forms.insert(word.clone());
+                                    forms.push(word);
                                 }
                             }
                         }
                     }
                 }
-                lemmas
+                (lemmas, forms)
             })
             .collect();
 
@@ -82,11 +261,18 @@ impl Searcher {
         let time_start = chrono::Utc::now();
 
         let mut thread_index = HashMap::new();
-        for (thread_id, lemmas) in thread_id_lemmas.into_iter().enumerate() {
+        let mut exact_index = HashMap::new();
+        for (thread_id, (lemmas, forms)) in thread_id_terms.into_iter().enumerate() {
             for lemma in lemmas {
                 thread_index
                     .entry(lemma)
-                    .or_insert_with(Vec::new)
+                    .or_insert_with(PostingList::new)
+                    .push(thread_id);
+            }
+            for form in forms {
+                exact_index
+                    .entry(form)
+                    .or_insert_with(PostingList::new)
                     .push(thread_id);
             }
         }
@@ -95,68 +281,931 @@ impl Searcher {
             "Creating HashMap took {:?}",
             chrono::Utc::now() - time_start
         );
+        let time_start = chrono::Utc::now();
+
+        let message_positions: Vec<Vec<(String, usize)>> = messages
+            .par_iter()
+            .map(|message| {
+                let mut positions = Vec::new();
+                let mut position = 0;
+                // words of links, code and mentions aren't indexed but still keep the words
+                // around them apart
+                for text_entity in &message.text_entities {
+                    for token in Tokenizer::tokenize(&text_entity.text) {
+                        if text_entity.is_lemmatizable() {
+                            for term in token.terms() {
+                                if tokenizer.is_indexable(&term) {
+                                    let lemma = lemmatizer.lemmatize(&term).to_string();
+                                    positions.push((lemma, position));
+                                }
+                            }
+                        }
+                        position += 1;
+                    }
+                }
+                positions
+            })
+            .collect();
+
+        let mut positional_index: HashMap<String, PositionList> = HashMap::new();
+        for (message_id, positions) in message_positions.into_iter().enumerate() {
+            for (lemma, position) in positions {
+                positional_index
+                    .entry(lemma)
+                    .or_default()
+                    .push(message_id, position);
+            }
+        }
+
+        utils::log!(
+            "Creating positional index took {:?}",
+            chrono::Utc::now() - time_start
+        );
+        let time_start = chrono::Utc::now();
+
+        let mut field_index = HashMap::new();
+        for message in &messages {
+            for text_entity in &message.text_entities {
+                if let Some(field) = text_entity.field() {
+                    let texts = field_index.entry(field).or_insert_with(Vec::new);
+                    for text in text_entity.field_texts() {
+                        texts.push((message.id, normalize(text)));
+                    }
+                }
+            }
+        }
+
+        let mut link_index = Vec::new();
+        let mut domain_index: HashMap<String, Vec<usize>> = HashMap::new();
+        for message in &messages {
+            for url in message.text_entities.iter().filter_map(TextEntity::url) {
+                if let Some(url) = parse_url(url) {
+                    let thread_ids = domain_index.entry(url.domain.clone()).or_default();
+                    let thread_id = thread_by_message[message.id];
+                    if thread_ids.last() != Some(&thread_id) {
+                        thread_ids.push(thread_id);
+                    }
+                    let normalized_url = normalize(&url.url);
+                    link_index.push((message.id, url, normalized_url));
+                }
+            }
+        }
+        for thread_ids in domain_index.values_mut() {
+            thread_ids.sort();
+            thread_ids.dedup();
+        }
+
+        let mut sender_index: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut message_counts: HashMap<&str, usize> = HashMap::new();
+        for message in &messages {
+            if let Some(from) = &message.from {
+                *message_counts.entry(from).or_default() += 1;
+                let thread_ids = sender_index.entry(from.clone()).or_default();
+                thread_ids.push(thread_by_message[message.id]);
+            }
+        }
+        for thread_ids in sender_index.values_mut() {
+            thread_ids.sort();
+            thread_ids.dedup();
+        }
+        let mut senders: Vec<String> = message_counts.keys().map(|from| from.to_string()).collect();
+        senders.sort_by_key(|from| Reverse(message_counts[from.as_str()]));
+
+        let term_dictionary = TermDictionary::new(thread_index.keys().cloned().collect());
+        let form_dictionary = TermDictionary::new(exact_index.keys().cloned().collect());
+
+        let mut thread_norms = vec![0.0; threads.len()];
+        for thread_ids in thread_index.values() {
+            let weight = idf(threads.len(), thread_ids.len());
+            for thread_id in thread_ids.iter() {
+                thread_norms[thread_id] += weight * weight;
+            }
+        }
+        for norm in &mut thread_norms {
+            *norm = norm.sqrt();
+        }
+
+        utils::log!(
+            "Creating term dictionary took {:?}",
+            chrono::Utc::now() - time_start
+        );
+
+        let thread_stats = threads
+            .par_iter()
+            .map(|message_ids| ThreadStats::new(message_ids, &messages))
+            .collect();
 
         Ok(Self {
             messages,
             threads,
+            thread_stats,
             lemmatizer,
+            tokenizer,
             thread_index,
+            thread_norms,
+            exact_index,
+            positional_index,
+            field_index,
+            link_index,
+            domain_index,
+            sender_index,
+            senders,
+            thread_by_message,
+            term_dictionary,
+            form_dictionary,
+            synonyms: Synonyms::default(),
+            utc_offset: |_| 0,
         })
     }
 
+    pub fn set_synonyms(&mut self, text: &str) {
+        self.synonyms = Synonyms::parse(text, self.lemmatizer);
+    }
+
+    /// How far the local time is ahead of UTC at a given local time, in seconds. Dates of
+    /// `date:` and of jumps through the chat are local days, UTC ones until this is set.
+    pub fn set_utc_offset(&mut self, utc_offset: fn(NaiveDateTime) -> i32) {
+        self.utc_offset = utc_offset;
+    }
+
+    /// Drops the words that are never indexed (stopwords, too short words), so that
+    /// `word & stopword` behaves like `word`. Returns `None` if nothing is left.
+    fn prune_query(&self, query: SearchQuery) -> Option<SearchQuery> {
+        match query {
+            SearchQuery::Word(word) => {
+                if self.tokenizer.is_indexable(&normalize(&word)) {
+                    Some(SearchQuery::Word(word))
+                } else {
+                    None
+                }
+            }
+            SearchQuery::Exact(word) => {
+                if self.tokenizer.is_indexable(&normalize(&word)) {
+                    Some(SearchQuery::Exact(word))
+                } else {
+                    None
+                }
+            }
+            SearchQuery::Lemma(lemma) => Some(SearchQuery::Lemma(lemma)),
+            SearchQuery::Fuzzy(word) => Some(SearchQuery::Fuzzy(word)),
+            SearchQuery::Regex(pattern) => Some(SearchQuery::Regex(pattern)),
+            SearchQuery::Field(field) => Some(SearchQuery::Field(field)),
+            SearchQuery::Wildcard(pattern) => {
+                if pattern.chars().all(|c| c == '*') {
+                    None
+                } else {
+                    Some(SearchQuery::Wildcard(pattern))
+                }
+            }
+            SearchQuery::Or((query_left, query_right)) => {
+                match (
+                    self.prune_query(*query_left),
+                    self.prune_query(*query_right),
+                ) {
+                    (Some(left), Some(right)) => {
+                        Some(SearchQuery::Or((Box::new(left), Box::new(right))))
+                    }
+                    (left, right) => left.or(right),
+                }
+            }
+            SearchQuery::And((query_left, query_right)) => {
+                match (
+                    self.prune_query(*query_left),
+                    self.prune_query(*query_right),
+                ) {
+                    (Some(left), Some(right)) => {
+                        Some(SearchQuery::And((Box::new(left), Box::new(right))))
+                    }
+                    (left, right) => left.or(right),
+                }
+            }
+            SearchQuery::Not(query) => self
+                .prune_query(*query)
+                .map(|query| SearchQuery::Not(Box::new(query))),
+            SearchQuery::Near((query_left, query_right, distance)) => {
+                match (
+                    self.prune_query(*query_left),
+                    self.prune_query(*query_right),
+                ) {
+                    (Some(left), Some(right)) => Some(SearchQuery::Near((
+                        Box::new(left),
+                        Box::new(right),
+                        distance,
+                    ))),
+                    (left, right) => left.or(right),
+                }
+            }
+        }
+    }
+
+    fn expand_synonyms(&self, query: SearchQuery) -> SearchQuery {
+        match query {
+            SearchQuery::Word(word) => {
+                let word = normalize(&word);
+                let lemma = self.lemmatizer.lemmatize(&word);
+                // the synonyms are lemmas already
+                self.synonyms
+                    .expand(lemma)
+                    .into_iter()
+                    .map(SearchQuery::Lemma)
+                    .reduce(|left, right| SearchQuery::Or((Box::new(left), Box::new(right))))
+                    .unwrap()
+            }
+            SearchQuery::Lemma(lemma) => SearchQuery::Lemma(lemma),
+            SearchQuery::Exact(word) => SearchQuery::Exact(word),
+            SearchQuery::Fuzzy(word) => SearchQuery::Fuzzy(word),
+            SearchQuery::Regex(pattern) => SearchQuery::Regex(pattern),
+            SearchQuery::Field(field) => SearchQuery::Field(field),
+            SearchQuery::Wildcard(pattern) => SearchQuery::Wildcard(pattern),
+            SearchQuery::Or((query_left, query_right)) => SearchQuery::Or((
+                Box::new(self.expand_synonyms(*query_left)),
+                Box::new(self.expand_synonyms(*query_right)),
+            )),
+            SearchQuery::And((query_left, query_right)) => SearchQuery::And((
+                Box::new(self.expand_synonyms(*query_left)),
+                Box::new(self.expand_synonyms(*query_right)),
+            )),
+            SearchQuery::Not(query) => SearchQuery::Not(Box::new(self.expand_synonyms(*query))),
+            SearchQuery::Near((query_left, query_right, distance)) => SearchQuery::Near((
+                Box::new(self.expand_synonyms(*query_left)),
+                Box::new(self.expand_synonyms(*query_right)),
+                distance,
+            )),
+        }
+    }
+
     fn find_threads_by_word(&self, word: String) -> Vec<usize> {
         utils::log!("find_threads_by_word({})", word);
-        let word = word.to_lowercase();
-        let word = self.lemmatizer.lemmatize(&word).to_string();
-        self.thread_index.get(&word).cloned().unwrap_or_default()
+        let word = normalize(&word);
+        self.find_threads_by_lemma(self.lemmatizer.lemmatize(&word))
+    }
+
+    fn find_threads_by_lemma(&self, lemma: &str) -> Vec<usize> {
+        self.thread_index
+            .get(lemma)
+            .map(PostingList::to_vec)
+            .unwrap_or_default()
+    }
+
+    fn find_threads_by_exact_word(&self, word: String) -> Vec<usize> {
+        utils::log!("find_threads_by_exact_word({})", word);
+        let word = normalize(&word);
+        self.exact_index
+            .get(&word)
+            .map(PostingList::to_vec)
+            .unwrap_or_default()
+    }
+
+    /// Index terms within a few typos of the lemmatized word.
+    fn find_fuzzy_terms(&self, word: &str) -> Vec<String> {
+        let word = normalize(word);
+        let lemma = self.lemmatizer.lemmatize(&word);
+        self.term_dictionary
+            .find_similar(lemma, max_edit_distance(lemma))
+            .into_iter()
+            .map(|(_, term)| term.to_string())
+            .collect()
+    }
+
+    fn find_threads_by_fuzzy_word(&self, word: String) -> Vec<usize> {
+        utils::log!("find_threads_by_fuzzy_word({})", word);
+        let postings: Vec<Vec<usize>> = self
+            .find_fuzzy_terms(&word)
+            .iter()
+            .map(|term| self.thread_index[term].to_vec())
+            .collect();
+        union_all(&postings)
+    }
+
+    /// Index terms matching the pattern, the most frequent ones first.
+    fn find_wildcard_terms(&self, pattern: &str) -> Vec<String> {
+        let mut terms = self.term_dictionary.find_wildcard(&normalize(pattern));
+        terms.sort_by_key(|term| Reverse(self.thread_index[*term].len()));
+        terms.into_iter().map(String::from).collect()
+    }
+
+    fn find_threads_by_wildcard(&self, pattern: String) -> Vec<usize> {
+        utils::log!("find_threads_by_wildcard({})", pattern);
+        let postings: Vec<Vec<usize>> = self
+            .find_wildcard_terms(&pattern)
+            .iter()
+            .take(MAX_WILDCARD_TERMS)
+            .map(|term| self.thread_index[term].to_vec())
+            .collect();
+        union_all(&postings)
+    }
+
+    /// Scans the text of every message, there is no index to help with regexes.
+    fn find_threads_by_regex(&self, pattern: String) -> Result<Vec<usize>, QueryError> {
+        utils::log!("find_threads_by_regex({})", pattern);
+        let regex = compile_regex(&pattern)?;
+        Ok((0..self.threads.len())
+            .into_par_iter()
+            .filter(|thread_id| {
+                self.threads[*thread_id]
+                    .iter()
+                    .any(|message_id| regex.is_match(&self.messages[*message_id].text()))
+            })
+            .collect())
+    }
+
+    /// Threads with an entity of the field containing the value, the entities are few
+    /// compared to the words, so they are simply scanned.
+    fn find_threads_by_field(&self, field: String, value: String) -> Vec<usize> {
+        utils::log!("find_threads_by_field({}, {})", field, value);
+        let value = normalize(&value);
+        let mut thread_ids: Vec<usize> = self
+            .field_index
+            .get(field.as_str())
+            .into_iter()
+            .flatten()
+            .filter(|(_, text)| text.contains(&value))
+            .map(|(message_id, _)| self.thread_by_message[*message_id])
+            .collect();
+        thread_ids.sort();
+        thread_ids.dedup();
+        thread_ids
+    }
+
+    fn find_threads_by_url(&self, value: String) -> Vec<usize> {
+        utils::log!("find_threads_by_url({})", value);
+        let value = normalize(&value);
+        let mut thread_ids: Vec<usize> = self
+            .link_index
+            .iter()
+            .filter(|(_, _, normalized_url)| normalized_url.contains(&value))
+            .map(|(message_id, _, _)| self.thread_by_message[*message_id])
+            .collect();
+        thread_ids.sort();
+        thread_ids.dedup();
+        thread_ids
+    }
+
+    /// Threads linking to the domain or any of its subdomains.
+    fn find_threads_by_domain(&self, value: String) -> Vec<usize> {
+        utils::log!("find_threads_by_domain({})", value);
+        let value = normalize(&value);
+        let postings: Vec<&Vec<usize>> = self
+            .domain_index
+            .iter()
+            .filter(|(domain, _)| matches_domain(domain, &value))
+            .map(|(_, thread_ids)| thread_ids)
+            .collect();
+        union_all(&postings)
+    }
+
+    fn find_threads_by_sender(&self, value: String) -> Vec<usize> {
+        utils::log!("find_threads_by_sender({})", value);
+        let value = normalize(&value);
+        let postings: Vec<&Vec<usize>> = self
+            .sender_index
+            .iter()
+            .filter(|(from, _)| normalize(from).contains(&value))
+            .map(|(_, thread_ids)| thread_ids)
+            .collect();
+        union_all(&postings)
+    }
+
+    /// Threads with a message from the given year, month or day. The messages are
+    /// stored in the order they were sent, so the day range is a range of messages.
+    fn find_threads_by_date(&self, value: String) -> Result<Vec<usize>, QueryError> {
+        utils::log!("find_threads_by_date({})", value);
+        let (start, end) = self.resolve_date_range(&value)?;
+        let first = self
+            .messages
+            .partition_point(|message| i64::from(message.date_unixtime) < start);
+        let last = self
+            .messages
+            .partition_point(|message| i64::from(message.date_unixtime) < end);
+        let mut thread_ids: Vec<usize> = self.thread_by_message[first..last].to_vec();
+        thread_ids.sort();
+        thread_ids.dedup();
+        Ok(thread_ids)
+    }
+
+    /// The lemmas a NEAR operand stands for.
+    fn find_near_terms(&self, query: &SearchQuery) -> Result<Vec<String>, QueryError> {
+        Ok(match query {
+            SearchQuery::Word(word) => {
+                vec![self.lemmatizer.lemmatize(&normalize(word)).to_string()]
+            }
+            SearchQuery::Lemma(lemma) => vec![lemma.clone()],
+            SearchQuery::Fuzzy(word) => self.find_fuzzy_terms(word),
+            SearchQuery::Wildcard(pattern) => self
+                .find_wildcard_terms(pattern)
+                .into_iter()
+                .take(MAX_WILDCARD_TERMS)
+                .collect(),
+            SearchQuery::Or((query_left, query_right)) => {
+                let mut terms = self.find_near_terms(query_left)?;
+                terms.extend(self.find_near_terms(query_right)?);
+                terms
+            }
+            _ => {
+                return Err(QueryError::new(
+                    "NEAR can only combine words and alternatives of words".to_string(),
+                ))
+            }
+        })
+    }
+
+    fn find_positions(&self, terms: &[String]) -> Vec<(usize, usize)> {
+        let mut positions: Vec<(usize, usize)> = terms
+            .iter()
+            .filter_map(|term| self.positional_index.get(term))
+            .flat_map(PositionList::iter)
+            .collect();
+        positions.sort();
+        positions.dedup();
+        positions
+    }
+
+    fn find_threads_by_near(
+        &self,
+        query_left: SearchQuery,
+        query_right: SearchQuery,
+        distance: usize,
+    ) -> Result<Vec<usize>, QueryError> {
+        let left = self.find_positions(&self.find_near_terms(&query_left)?);
+        let right = self.find_positions(&self.find_near_terms(&query_right)?);
+        let mut thread_ids: Vec<usize> = find_near_messages(&left, &right, distance)
+            .into_iter()
+            .map(|message_id| self.thread_by_message[message_id])
+            .collect();
+        thread_ids.sort();
+        thread_ids.dedup();
+        Ok(thread_ids)
+    }
+
+    fn find_threads_by_query(&self, query: SearchQuery) -> Result<Vec<usize>, QueryError> {
+        self.find_threads_by_plan(QueryPlan::from(query))
     }
 
-    fn find_threads_by_query(&self, query: SearchQuery) -> Vec<usize> {
+    /// Reports the errors of every operand up front, the evaluation stops at the first
+    /// empty operand of AND and would leave the rest unchecked.
+    fn validate_query(&self, query: &SearchQuery) -> Result<(), QueryError> {
         match query {
+            SearchQuery::Regex(pattern) => compile_regex(pattern).map(|_| ()),
+            SearchQuery::Field((field, value)) if field == "date" => {
+                self.resolve_date_range(value).map(|_| ())
+            }
+            SearchQuery::Near((query_left, query_right, _)) => {
+                self.find_near_terms(query_left)?;
+                self.find_near_terms(query_right).map(|_| ())
+            }
+            SearchQuery::And((query_left, query_right))
+            | SearchQuery::Or((query_left, query_right)) => {
+                self.validate_query(query_left)?;
+                self.validate_query(query_right)
+            }
+            SearchQuery::Not(query) => self.validate_query(query),
+            SearchQuery::Word(_)
+            | SearchQuery::Lemma(_)
+            | SearchQuery::Fuzzy(_)
+            | SearchQuery::Wildcard(_)
+            | SearchQuery::Exact(_)
+            | SearchQuery::Field(_) => Ok(()),
+        }
+    }
+
+    fn find_threads_by_plan(&self, plan: QueryPlan) -> Result<Vec<usize>, QueryError> {
+        match plan {
+            QueryPlan::Leaf(query) => self.find_threads_by_leaf(query),
+            QueryPlan::And(operands) => self.find_threads_by_and(operands),
+            QueryPlan::Or(operands) => {
+                let postings = operands
+                    .into_iter()
+                    .map(|operand| self.find_threads_by_plan(operand))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(union_all(&postings))
+            }
+            QueryPlan::Not(plan) => {
+                let all_threads: Vec<usize> = (0..self.threads.len()).collect();
+                Ok(difference(&all_threads, &self.find_threads_by_plan(*plan)?))
+            }
+        }
+    }
+
+    /// Intersects the operands starting with the shortest ones and stops as soon as nothing
+    /// is left, so that a rare word spares the scan of a regex next to it. Negated operands
+    /// are subtracted at the end instead of being complemented.
+    fn find_threads_by_and(&self, operands: Vec<QueryPlan>) -> Result<Vec<usize>, QueryError> {
+        let (excluded, mut included): (Vec<QueryPlan>, Vec<QueryPlan>) = operands
+            .into_iter()
+            .partition(|operand| matches!(operand, QueryPlan::Not(_)));
+        if included.is_empty() {
+            let excluded = excluded
+                .into_iter()
+                .map(|operand| match operand {
+                    QueryPlan::Not(plan) => *plan,
+                    plan => plan,
+                })
+                .collect();
+            return self.find_threads_by_plan(QueryPlan::Not(Box::new(QueryPlan::Or(excluded))));
+        }
+        included.sort_by_cached_key(|operand| self.estimate_threads_count(operand));
+
+        let mut thread_ids: Option<Vec<usize>> = None;
+        for operand in included {
+            let result = match thread_ids {
+                Some(thread_ids) => match self.get_posting_list(&operand) {
+                    Some(posting_list) => posting_list.intersect(&thread_ids),
+                    None => intersect(&thread_ids, &self.find_threads_by_plan(operand)?),
+                },
+                None => self.find_threads_by_plan(operand)?,
+            };
+            if result.is_empty() {
+                return Ok(result);
+            }
+            thread_ids = Some(result);
+        }
+        let mut thread_ids = thread_ids.unwrap_or_default();
+        for operand in excluded {
+            if let QueryPlan::Not(plan) = operand {
+                thread_ids = difference(&thread_ids, &self.find_threads_by_plan(*plan)?);
+            }
+            if thread_ids.is_empty() {
+                break;
+            }
+        }
+        Ok(thread_ids)
+    }
+
+    /// The posting list of a single word, which can be intersected without decoding it all.
+    fn get_posting_list(&self, plan: &QueryPlan) -> Option<&PostingList> {
+        match plan {
+            QueryPlan::Leaf(SearchQuery::Word(word)) => {
+                let word = normalize(word);
+                let lemma = self.lemmatizer.lemmatize(&word);
+                Some(self.thread_index.get(lemma).unwrap_or(&EMPTY_POSTING_LIST))
+            }
+            QueryPlan::Leaf(SearchQuery::Lemma(lemma)) => {
+                Some(self.thread_index.get(lemma).unwrap_or(&EMPTY_POSTING_LIST))
+            }
+            QueryPlan::Leaf(SearchQuery::Exact(word)) => Some(
+                self.exact_index
+                    .get(&normalize(word))
+                    .unwrap_or(&EMPTY_POSTING_LIST),
+            ),
+            _ => None,
+        }
+    }
+
+    /// An upper bound of the number of threads found by the plan that is cheap to get.
+    /// Queries without a posting list are expected to match everything, and regexes,
+    /// which scan every message, go last.
+    fn estimate_threads_count(&self, plan: &QueryPlan) -> usize {
+        match plan {
+            QueryPlan::Leaf(SearchQuery::Word(word)) => {
+                let word = normalize(word);
+                let lemma = self.lemmatizer.lemmatize(&word);
+                self.thread_index.get(lemma).map_or(0, PostingList::len)
+            }
+            QueryPlan::Leaf(SearchQuery::Lemma(lemma)) => {
+                self.thread_index.get(lemma).map_or(0, PostingList::len)
+            }
+            QueryPlan::Leaf(SearchQuery::Exact(word)) => self
+                .exact_index
+                .get(&normalize(word))
+                .map_or(0, PostingList::len),
+            QueryPlan::Leaf(SearchQuery::Regex(_)) => usize::MAX,
+            QueryPlan::Leaf(_) | QueryPlan::Not(_) => self.threads.len(),
+            QueryPlan::And(operands) => operands
+                .iter()
+                .map(|operand| self.estimate_threads_count(operand))
+                .min()
+                .unwrap_or_default(),
+            QueryPlan::Or(operands) => operands
+                .iter()
+                .map(|operand| self.estimate_threads_count(operand))
+                .fold(0, usize::saturating_add),
+        }
+    }
+
+    fn find_threads_by_leaf(&self, query: SearchQuery) -> Result<Vec<usize>, QueryError> {
+        Ok(match query {
             SearchQuery::Word(word) => self.find_threads_by_word(word),
-            SearchQuery::Or((query_left, query_right)) => MergeOr::new(
-                self.find_threads_by_query(*query_left).iter(),
-                self.find_threads_by_query(*query_right).iter(),
-            )
-            .copied()
-            .collect(),
-            SearchQuery::And((query_left, query_right)) => MergeAnd::new(
-                self.find_threads_by_query(*query_left).iter(),
-                self.find_threads_by_query(*query_right).iter(),
-            )
-            .copied()
-            .collect(),
-        }
-    }
-
-    pub fn get_query_words(&self, query: String) -> Vec<String> {
-        query
-            .to_lowercase()
-            .split(|c: char| !c.is_alphanumeric())
-            .filter(|word| word.len() > 3)
-            .map(|word| self.lemmatizer.lemmatize(word).to_string())
-            .collect()
+            SearchQuery::Lemma(lemma) => self.find_threads_by_lemma(&lemma),
+            SearchQuery::Exact(word) => self.find_threads_by_exact_word(word),
+            SearchQuery::Fuzzy(word) => self.find_threads_by_fuzzy_word(word),
+            SearchQuery::Wildcard(pattern) => self.find_threads_by_wildcard(pattern),
+            SearchQuery::Regex(pattern) => self.find_threads_by_regex(pattern)?,
+            SearchQuery::Field((field, value)) => match field.as_str() {
+                "url" => self.find_threads_by_url(value),
+                "domain" => self.find_threads_by_domain(value),
+                "from" => self.find_threads_by_sender(value),
+                "date" => self.find_threads_by_date(value)?,
+                _ => self.find_threads_by_field(field, value),
+            },
+            SearchQuery::Near((query_left, query_right, distance)) => {
+                self.find_threads_by_near(*query_left, *query_right, distance)?
+            }
+            query @ (SearchQuery::And(_) | SearchQuery::Or(_) | SearchQuery::Not(_)) => {
+                self.find_threads_by_query(query)?
+            }
+        })
     }
 
-    pub fn find_threads(&self, query: String) -> anyhow::Result<Vec<ThreadSearchResult>> {
-        let query = Parser::new(Lexer::new(&query))?.parse()?;
+    pub fn get_query_words(&self, query: String) -> QueryWords {
+        let mut words = QueryWords::default();
+        match Parser::new(Lexer::new(&query)).and_then(|mut parser| parser.parse()) {
+            Ok(query) => {
+                if let Some(query) = self.prune_query(query) {
+                    self.collect_query_words(&self.expand_synonyms(query), &mut words);
+                }
+            }
+            Err(_) => {
+                words.lemmas = self
+                    .tokenizer
+                    .words(&query)
+                    .flat_map(|word| self.synonyms.expand(self.lemmatizer.lemmatize(&word)))
+                    .collect()
+            }
+        }
+        words
+    }
+
+    fn collect_query_words(&self, query: &SearchQuery, words: &mut QueryWords) {
+        match query {
+            SearchQuery::Word(word) => words
+                .lemmas
+                .push(self.lemmatizer.lemmatize(&normalize(word)).to_string()),
+            SearchQuery::Lemma(lemma) => words.lemmas.push(lemma.clone()),
+            SearchQuery::Exact(word) => words.exact_forms.push(normalize(word)),
+            SearchQuery::Regex(pattern) => words.regexes.push(pattern.clone()),
+            SearchQuery::Field((field, value)) => {
+                words.fields.push((field.clone(), normalize(value)))
+            }
+            // excluded words are never present in the found threads
+            SearchQuery::Not(_) => {}
+            SearchQuery::Fuzzy(word) => words.lemmas.extend(self.find_fuzzy_terms(word)),
+            SearchQuery::Wildcard(pattern) => words.lemmas.extend(
+                self.find_wildcard_terms(pattern)
+                    .into_iter()
+                    .take(MAX_WILDCARD_TERMS),
+            ),
+            SearchQuery::Or((query_left, query_right))
+            | SearchQuery::And((query_left, query_right))
+            | SearchQuery::Near((query_left, query_right, _)) => {
+                self.collect_query_words(query_left, words);
+                self.collect_query_words(query_right, words);
+            }
+        }
+    }
+
+    /// Explains the parts of the query that were not searched for exactly as written.
+    pub fn get_query_warnings(&self, query: String) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Ok(query) = Parser::new(Lexer::new(&query)).and_then(|mut parser| parser.parse()) {
+            self.collect_query_warnings(&query, &mut warnings);
+        }
+        warnings
+    }
+
+    fn collect_query_warnings(&self, query: &SearchQuery, warnings: &mut Vec<String>) {
+        match query {
+            SearchQuery::Wildcard(pattern) => {
+                let terms_count = self.find_wildcard_terms(pattern).len();
+                if terms_count > MAX_WILDCARD_TERMS {
+                    warnings.push(format!(
+                        "\"{}\" matches {} words, only the {} most frequent ones are searched for",
+                        pattern, terms_count, MAX_WILDCARD_TERMS
+                    ));
+                }
+            }
+            SearchQuery::Or((query_left, query_right))
+            | SearchQuery::And((query_left, query_right))
+            | SearchQuery::Near((query_left, query_right, _)) => {
+                self.collect_query_warnings(query_left, warnings);
+                self.collect_query_warnings(query_right, warnings);
+            }
+            SearchQuery::Not(query) => self.collect_query_warnings(query, warnings),
+            SearchQuery::Word(_)
+            | SearchQuery::Lemma(_)
+            | SearchQuery::Exact(_)
+            | SearchQuery::Fuzzy(_)
+            | SearchQuery::Regex(_)
+            | SearchQuery::Field(_) => {}
+        }
+    }
+
+    /// Replaces the query words that are not in the index with the closest indexed word
+    /// forms, preferring the more frequent ones. Returns `None` if there is nothing to correct.
+    pub fn suggest(&self, query: String) -> Option<String> {
+        let mut suggestion = String::new();
+        let mut last_end = 0;
+        let regexes = find_regex_spans(&query);
+        for token in Tokenizer::tokenize(&query) {
+            let lemma = self.lemmatizer.lemmatize(&token.text);
+            let is_regex = regexes.iter().any(|span| span.contains(&token.span.start));
+            let is_wildcard = query[token.span.end..].starts_with('*')
+                || query[..token.span.start].ends_with('*');
+            let is_operator = matches!(&query[token.span.clone()], "NOT" | "NEAR");
+            let chunk_start = query[..token.span.start]
+                .rfind(char::is_whitespace)
+                .map_or(0, |i| i + 1);
+            let is_field = query[token.span.end..].starts_with(':')
+                || query[chunk_start..token.span.start].contains(':');
+            if is_regex
+                || is_wildcard
+                || is_operator
+                || is_field
+                || !self.tokenizer.is_indexable(&token.text)
+                || self.thread_index.contains_key(lemma)
+            {
+                continue;
+            }
+            // the form as typed, not its lemma, so that "деплоили" isn't corrected to "деплоить"
+            let closest_term = self
+                .form_dictionary
+                .find_similar(&token.text, max_edit_distance(&token.text))
+                .into_iter()
+                .min_by_key(|(distance, term)| (*distance, Reverse(self.exact_index[*term].len())));
+            if let Some((_, term)) = closest_term {
+                suggestion.push_str(&query[last_end..token.span.start]);
+                suggestion.push_str(term);
+                last_end = token.span.end;
+            }
+        }
+        if last_end == 0 {
+            return None;
+        }
+        suggestion.push_str(&query[last_end..]);
+        Some(suggestion)
+    }
 
-        let mut result: Vec<ThreadSearchResult> = self
-            .find_threads_by_query(query)
+    /// Ways to finish the last word of the query: field names, sender names after
+    /// `from:`, and index terms starting with the word, the most frequent ones first.
+    pub fn complete(&self, query: String) -> Vec<Completion> {
+        let mut in_quotes = false;
+        let mut last_word_start = 0;
+        for (i, c) in query.char_indices() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else if !in_quotes && (c.is_whitespace() || "()|&".contains(c)) {
+                last_word_start = i + c.len_utf8();
+            }
+        }
+        let last_word = &query[last_word_start..];
+        let word = last_word.trim_start_matches(['-', '!', '=', '\'']);
+        let word_start = last_word_start + last_word.len() - word.len();
+        let completion = |label: String, completed: &str| Completion {
+            label,
+            query: format!("{}{}", &query[..word_start], completed),
+        };
+
+        if let Some((field, value)) = word.split_once(':') {
+            if field != "from" {
+                return Vec::new();
+            }
+            let value = normalize(value.trim_start_matches('"'));
+            return self
+                .senders
+                .iter()
+                .filter(|from| {
+                    let from = normalize(from);
+                    from.starts_with(&value)
+                        || from.split_whitespace().any(|part| part.starts_with(&value))
+                })
+                .take(MAX_COMPLETIONS)
+                .map(|from| {
+                    let completed = if from.contains(char::is_whitespace) {
+                        format!("from:\"{}\" ", from)
+                    } else {
+                        format!("from:{} ", from)
+                    };
+                    completion(from.clone(), &completed)
+                })
+                .collect();
+        }
+
+        let word = normalize(word);
+        if word.is_empty() || !word.chars().all(is_word_char) {
+            return Vec::new();
+        }
+        let mut completions: Vec<Completion> = field_names()
             .into_iter()
-            .map(|thread_id| {
-                let message_id = self.threads[thread_id].first().copied().unwrap();
-                let message = &self.messages[message_id];
-                ThreadSearchResult {
-                    thread_id: thread_id as u32,
-                    score: 0,
-                    title_text: message.clone().into(),
-                    date_unixtime: message.date_unixtime,
+            .filter(|field| field.starts_with(&word))
+            .map(|field| completion(format!("{}:", field), &format!("{}:", field)))
+            .collect();
+        if word.chars().count() >= 2 {
+            let mut terms = self.term_dictionary.find_wildcard(&format!("{}*", word));
+            terms.retain(|term| *term != word);
+            terms.sort_by_key(|term| Reverse(self.thread_index[*term].len()));
+            completions.extend(
+                terms
+                    .into_iter()
+                    .map(|term| completion(term.to_string(), term)),
+            );
+        }
+        completions.truncate(MAX_COMPLETIONS);
+        completions
+    }
+
+    /// The threads matching the query, the latest first.
+    pub fn find_thread_ids(&self, query: String) -> Result<Vec<usize>, QueryError> {
+        let query = Parser::new(Lexer::new(&query))?.parse()?;
+        let query = match self.prune_query(query) {
+            Some(query) => self.expand_synonyms(query),
+            None => return Ok(Vec::new()),
+        };
+        self.validate_query(&query)?;
+        let mut thread_ids = self.find_threads_by_query(query)?;
+        thread_ids.sort_by_key(|thread_id| {
+            Reverse(self.messages[self.threads[*thread_id][0]].date_unixtime)
+        });
+        Ok(thread_ids)
+    }
+
+    /// The found threads highlighted and summarized, only the ones shown are worth it.
+    pub fn get_thread_results(
+        &self,
+        thread_ids: &[usize],
+        query_words: &QueryWords,
+    ) -> Vec<ThreadSearchResult> {
+        let regexes = compile_regexes(query_words);
+        thread_ids
+            .par_iter()
+            .map(|thread_id| self.get_thread_result(*thread_id, query_words, &regexes))
+            .collect()
+    }
+
+    /// Threads about the same topic as the given one, the most similar first. Threads are
+    /// compared by the cosine of their lemma vectors weighted by IDF, a lemma counts once
+    /// per thread, the same as in the index.
+    pub fn similar_threads(&self, thread_id: usize) -> Vec<ThreadSearchResult> {
+        utils::log!("similar_threads({})", thread_id);
+        let mut lemmas = HashSet::new();
+        for message_id in &self.threads[thread_id] {
+            for text_entity in &self.messages[*message_id].text_entities {
+                if text_entity.is_lemmatizable() {
+                    for word in self.tokenizer.words(&text_entity.text) {
+                        lemmas.insert(self.lemmatizer.lemmatize(&word).to_string());
+                    }
                 }
+            }
+        }
+        let posting_lists: Vec<&PostingList> = lemmas
+            .iter()
+            .filter_map(|lemma| self.thread_index.get(lemma))
+            .collect();
+        let query_words = QueryWords::default();
+        rank_similar(&posting_lists, &self.thread_norms, thread_id)
+            .into_iter()
+            .take(MAX_SIMILAR_THREADS)
+            .map(|(similar_id, similarity)| ThreadSearchResult {
+                score: (similarity * 100.0).round() as u32,
+                ..self.get_thread_result(similar_id, &query_words, &[])
             })
+            .collect()
+    }
+
+    fn get_thread_result(
+        &self,
+        thread_id: usize,
+        query_words: &QueryWords,
+        regexes: &[Regex],
+    ) -> ThreadSearchResult {
+        let message_ids = &self.threads[thread_id];
+        let first_message = &self.messages[*message_ids.first().unwrap()];
+        let last_message = &self.messages[*message_ids.last().unwrap()];
+        let stats = &self.thread_stats[thread_id];
+        let hits: Vec<(usize, &Message)> = message_ids
+            .iter()
+            .map(|message_id| &self.messages[*message_id])
+            .map(|message| (self.count_hits(message, query_words, regexes), message))
+            .filter(|(hits_count, _)| *hits_count > 0)
             .collect();
-        result.sort_by_key(|thread| -(thread.date_unixtime as i32));
-        Ok(result)
+        // the earliest of the messages with the most hits
+        let best_message = hits
+            .iter()
+            .rev()
+            .max_by_key(|(hits_count, _)| *hits_count)
+            .map(|(_, message)| *message);
+        let title_message = match best_message {
+            Some(message) if !self.is_meaningful(first_message) => message,
+            _ => first_message,
+        };
+        let snippet = hits
+            .first()
+            .map(|(_, message)| {
+                let text =
+                    self.get_highlighted_text(message.text_entities.clone(), query_words, regexes);
+                make_snippet(text, SNIPPET_CONTEXT)
+            })
+            .unwrap_or_default();
+        ThreadSearchResult {
+            thread_id: thread_id as u32,
+            score: 0,
+            title_text: title_message.clone().into(),
+            snippet,
+            date_unixtime: first_message.date_unixtime,
+            last_date_unixtime: last_message.date_unixtime,
+            message_count: message_ids.len(),
+            participants: stats.participants.clone(),
+            matched_count: hits.len(),
+            reply_depth: stats.reply_depth,
+        }
     }
 
     pub fn get_thread_messages(&self, thread_id: usize) -> (usize, usize) {
@@ -166,65 +1215,950 @@ impl Searcher {
         (min_id, max_id)
     }
 
+    /// The messages of the thread arranged by replies, without the unrelated messages sent
+    /// in between. Usually there is a single root, the message everyone replied to.
+    pub fn get_thread_tree(&self, thread_id: usize, query_words: &QueryWords) -> Vec<MessageTree> {
+        utils::log!("get_thread_tree({})", thread_id);
+        let regexes = compile_regexes(query_words);
+        let message_ids = &self.threads[thread_id];
+        // a reply is always sent after the message it replies to, so the parents come first
+        let mut parents: HashMap<usize, (Option<usize>, usize)> = HashMap::new();
+        for message_id in message_ids {
+            let parent = match self.messages[*message_id].reply_to_message_id {
+                Some(reply_to_id) => parents.get(&reply_to_id).map(|(parent, depth)| {
+                    if *depth < MAX_TREE_DEPTH {
+                        (Some(reply_to_id), depth + 1)
+                    } else {
+                        (*parent, *depth)
+                    }
+                }),
+                None => None,
+            };
+            parents.insert(*message_id, parent.unwrap_or((None, 0)));
+        }
+
+        // the replies are assembled before their parents, in reverse, without recursion
+        let mut replies: HashMap<usize, Vec<MessageTree>> = HashMap::new();
+        let mut roots = Vec::new();
+        for message_id in message_ids.iter().rev() {
+            let mut tree = MessageTree {
+                message: self.get_message_result(
+                    &self.messages[*message_id],
+                    query_words,
+                    &regexes,
+                ),
+                replies: replies.remove(message_id).unwrap_or_default(),
+            };
+            tree.replies.reverse();
+            match parents[message_id].0 {
+                Some(parent_id) => replies.entry(parent_id).or_default().push(tree),
+                None => roots.push(tree),
+            }
+        }
+        roots.reverse();
+        roots
+    }
+
+    /// All links of the thread in the order they were posted, each one only once.
+    pub fn get_thread_links(&self, thread_id: usize) -> Vec<LinkResult> {
+        let mut used_urls = HashSet::new();
+        let mut links = Vec::new();
+        for message_id in &self.threads[thread_id] {
+            let start = self
+                .link_index
+                .partition_point(|(link_message_id, _, _)| link_message_id < message_id);
+            for (_, url, _) in self.link_index[start..]
+                .iter()
+                .take_while(|(link_message_id, _, _)| link_message_id == message_id)
+            {
+                if used_urls.insert(url.url.as_str()) {
+                    links.push(LinkResult {
+                        message_id: *message_id,
+                        url: url.url.clone(),
+                        domain: url.domain.clone(),
+                    });
+                }
+            }
+        }
+        links
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// The unix time range of a date in the local time, see `set_utc_offset`.
+    fn local_date_range(&self, value: &str) -> Option<(i64, i64)> {
+        let local = |timestamp: i64| {
+            let wall_time = DateTime::from_timestamp(timestamp, 0)?.naive_utc();
+            Some(timestamp - i64::from((self.utc_offset)(wall_time)))
+        };
+        let (start, end) = parse_date_range(value)?;
+        Some((local(start)?, local(end)?))
+    }
+
+    fn resolve_date_range(&self, value: &str) -> Result<(i64, i64), QueryError> {
+        self.local_date_range(value).ok_or_else(|| {
+            QueryError::new(format!(
+                "Invalid date {}, expected YYYY, YYYY-MM or YYYY-MM-DD",
+                value
+            ))
+        })
+    }
+
+    /// The first message sent on the local `YYYY-MM-DD` day or after it, the last message
+    /// if the chat ends before that day.
+    pub fn find_message_by_date(&self, date: &str) -> Option<usize> {
+        let (start, _) = self.local_date_range(date)?;
+        let message_id = self
+            .messages
+            .partition_point(|message| i64::from(message.date_unixtime) < start);
+        Some(message_id.min(self.messages.len().checked_sub(1)?))
+    }
+
+    /// The messages from `message_id_min` to `message_id_max` inclusive, the range is cut
+    /// to the messages of the chat.
     pub fn get_message_range(
         &self,
         message_id_min: usize,
         message_id_max: usize,
-        query_words: &[&str],
+        query_words: &QueryWords,
     ) -> Vec<MessageResult> {
-        if message_id_min > message_id_max {
+        let message_id_max = message_id_max.min(self.messages.len().saturating_sub(1));
+        if message_id_min > message_id_max || self.messages.is_empty() {
             return Vec::new();
         }
+        let regexes = compile_regexes(query_words);
         self.messages[message_id_min..=message_id_max]
             .iter()
-            .map(|message| {
-                let reply_to_text = message
-                    .reply_to_message_id
-                    .map(|reply_to_id| self.messages[reply_to_id].clone().into());
-                MessageResult {
-                    message_id: message.id,
-                    text: self.get_highlighted_text(message.text_entities.clone(), query_words),
-                    reply_to_text,
-                }
-            })
+            .map(|message| self.get_message_result(message, query_words, &regexes))
             .collect()
     }
 
-    fn get_highlighted_text(&self, text: Vec<TextEntity>, query_words: &[&str]) -> Vec<Text> {
+    fn get_message_result(
+        &self,
+        message: &Message,
+        query_words: &QueryWords,
+        regexes: &[Regex],
+    ) -> MessageResult {
+        let reply_to_text = message
+            .reply_to_message_id
+            .map(|reply_to_id| self.messages[reply_to_id].clone().into());
+        MessageResult {
+            message_id: message.id,
+            text: self.get_highlighted_text(message.text_entities.clone(), query_words, regexes),
+            reply_to_text,
+        }
+    }
+
+    fn get_highlighted_text(
+        &self,
+        text: Vec<TextEntity>,
+        query_words: &QueryWords,
+        regexes: &[Regex],
+    ) -> Vec<Text> {
         text.into_iter()
-            .flat_map(|text_entity| match text_entity {
-                TextEntity::Lemmatizable(text) => self.highlight_substrings(text, query_words),
-                TextEntity::Illemmatizable(text) => vec![Text::Plain(text)],
+            .flat_map(|text_entity| {
+                let ranges = self.find_highlight_ranges(&text_entity, query_words, regexes);
+                split_highlights(text_entity.text, ranges)
+            })
+            .collect()
+    }
+
+    /// How many times the query occurs in the message.
+    fn count_hits(&self, message: &Message, query_words: &QueryWords, regexes: &[Regex]) -> usize {
+        message
+            .text_entities
+            .iter()
+            .map(|text_entity| {
+                self.find_highlight_ranges(text_entity, query_words, regexes)
+                    .len()
+            })
+            .sum()
+    }
+
+    /// Whether the message says enough to title its thread.
+    fn is_meaningful(&self, message: &Message) -> bool {
+        message
+            .text_entities
+            .iter()
+            .filter(|text_entity| text_entity.is_lemmatizable())
+            .flat_map(|text_entity| self.tokenizer.words(&text_entity.text))
+            .nth(MIN_TITLE_WORDS - 1)
+            .is_some()
+    }
+
+    fn find_highlight_ranges(
+        &self,
+        text_entity: &TextEntity,
+        query_words: &QueryWords,
+        regexes: &[Regex],
+    ) -> Vec<Range<usize>> {
+        let mut ranges = find_regex_ranges(text_entity.text(), regexes);
+        if text_entity.is_lemmatizable() {
+            ranges.extend(self.find_word_ranges(&text_entity.text, query_words));
+        }
+        if query_words
+            .fields
+            .iter()
+            .any(|(name, value)| entity_matches_field(text_entity, name, value))
+        {
+            ranges.push(0..text_entity.text.len());
+        }
+        ranges
+    }
+
+    fn find_word_ranges(&self, target: &str, query_words: &QueryWords) -> Vec<Range<usize>> {
+        Tokenizer::tokenize(target)
+            .into_iter()
+            .filter(|token| {
+                token.terms().iter().any(|term| {
+                    query_words.exact_forms.contains(term)
+                        || query_words
+                            .lemmas
+                            .iter()
+                            .any(|lemma| lemma == self.lemmatizer.lemmatize(term))
+                })
             })
+            .map(|token| token.span)
             .collect()
     }
-    fn highlight_substrings(&self, target: String, queries: &[&str]) -> Vec<Text> {
-        let mut result = Vec::new();
-        let mut target = target;
-        let lemmatizer = self.lemmatizer;
-        while !target.is_empty() {
-            let next_non_alphanumeric = target.find(|c: char| !c.is_alphanumeric());
-            let (word, rest) = match next_non_alphanumeric {
-                Some(index) => {
-                    let (word, rest) = target.split_at(index);
-                    (word.to_string(), rest.to_string())
-                }
-                None => (target.clone(), "".into()),
+}
+
+/// The unix time range of a `YYYY`, `YYYY-MM` or `YYYY-MM-DD` date, the end is excluded.
+fn parse_date_range(value: &str) -> Option<(i64, i64)> {
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let (start, end) = match parts[..] {
+        [year] => (
+            NaiveDate::from_ymd_opt(year as i32, 1, 1)?,
+            NaiveDate::from_ymd_opt(year as i32 + 1, 1, 1)?,
+        ),
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, 1)?;
+            let end = if month == 12 {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(start.year(), month + 1, 1)?
             };
-            target = rest;
-            let lowercase = word.to_lowercase();
-            let lemmatized_word = lemmatizer.lemmatize(&lowercase);
-            if queries.contains(&lemmatized_word) {
-                result.push(Text::Highlight(word));
+            (start, end)
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+            (start, start.succ_opt()?)
+        }
+        _ => return None,
+    };
+    let timestamp = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+    Some((timestamp(start), timestamp(end)))
+}
+
+/// Whether the entity would be found by a `name:value` query, the value is normalized.
+fn entity_matches_field(text_entity: &TextEntity, name: &str, value: &str) -> bool {
+    match name {
+        "url" => text_entity
+            .url()
+            .is_some_and(|url| normalize(url).contains(value)),
+        "domain" => text_entity
+            .url()
+            .and_then(parse_url)
+            .is_some_and(|url| matches_domain(&url.domain, value)),
+        _ => {
+            text_entity.field() == Some(name)
+                && text_entity
+                    .field_texts()
+                    .any(|text| normalize(text).contains(value))
+        }
+    }
+}
+
+/// Messages where a left and a right position are at most `distance` words apart.
+/// Both lists are sorted (message_id, position) pairs.
+fn find_near_messages(
+    left: &[(usize, usize)],
+    right: &[(usize, usize)],
+    distance: usize,
+) -> Vec<usize> {
+    let mut message_ids = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        let (left_message, left_position) = left[i];
+        let (right_message, right_position) = right[j];
+        if left_message == right_message && left_position.abs_diff(right_position) <= distance {
+            message_ids.push(left_message);
+            while i < left.len() && left[i].0 == left_message {
+                i += 1;
+            }
+            while j < right.len() && right[j].0 == right_message {
+                j += 1;
+            }
+        } else if left[i] < right[j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    message_ids
+}
+
+/// Byte ranges of the `/regex/` parts of a query, read the way the lexer reads them.
+fn find_regex_spans(query: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut escaped = false;
+    for (i, c) in query.char_indices() {
+        match start {
+            Some(regex_start) => {
+                if c == '/' && !escaped {
+                    spans.push(regex_start..i + 1);
+                    start = None;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            None if c == '/' && !query[..i].ends_with("NEAR") => start = Some(i),
+            None => {}
+        }
+    }
+    spans.extend(start.map(|start| start..query.len()));
+    spans
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, QueryError> {
+    Regex::new(pattern).map_err(|error| QueryError::new(error.to_string()))
+}
+
+fn compile_regexes(query_words: &QueryWords) -> Vec<Regex> {
+    // the regexes have already been checked by `find_thread_ids`
+    query_words
+        .regexes
+        .iter()
+        .filter_map(|pattern| Regex::new(pattern).ok())
+        .collect()
+}
+
+fn find_regex_ranges(target: &str, regexes: &[Regex]) -> Vec<Range<usize>> {
+    regexes
+        .iter()
+        .flat_map(|regex| regex.find_iter(target))
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// Inverse document frequency of a lemma found in `thread_count` of all threads.
+fn idf(total_count: usize, thread_count: usize) -> f32 {
+    (total_count as f32 / thread_count as f32).ln()
+}
+
+/// Cosine similarities of the thread to every thread sharing a lemma with it, the most
+/// similar first. `posting_lists` are the threads of each lemma of the thread, the ones
+/// of near universal lemmas are skipped.
+fn rank_similar(
+    posting_lists: &[&PostingList],
+    norms: &[f32],
+    thread_id: usize,
+) -> Vec<(usize, f32)> {
+    let mut dot_products = vec![0.0; norms.len()];
+    for thread_ids in posting_lists {
+        let weight = idf(norms.len(), thread_ids.len());
+        if weight < MIN_SIMILARITY_IDF {
+            continue;
+        }
+        for other_id in thread_ids.iter() {
+            dot_products[other_id] += weight * weight;
+        }
+    }
+    let mut similar: Vec<(usize, f32)> = dot_products
+        .into_iter()
+        .enumerate()
+        .filter(|(other_id, dot_product)| *other_id != thread_id && *dot_product > 0.0)
+        .map(|(other_id, dot_product)| {
+            (other_id, dot_product / (norms[thread_id] * norms[other_id]))
+        })
+        .collect();
+    similar.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    similar
+}
+
+/// Keyword in context: the text around its first highlight, with at most `context`
+/// characters on each side cut at whitespace.
+fn make_snippet(text: Vec<Text>, context: usize) -> Vec<Text> {
+    let first_highlight = match text
+        .iter()
+        .position(|part| matches!(part, Text::Highlight(_)))
+    {
+        Some(index) => index,
+        None => return Vec::new(),
+    };
+    let mut snippet = Vec::new();
+
+    let before = text[..first_highlight]
+        .iter()
+        .map(|part| match part {
+            Text::Plain(text) | Text::Highlight(text) => text.as_str(),
+        })
+        .collect::<String>()
+        .replace('\n', " ");
+    let before_length = before.chars().count();
+    if before_length > context {
+        let cut: String = before.chars().skip(before_length - context).collect();
+        let cut = match cut.find(char::is_whitespace) {
+            Some(index) => cut[index..].trim_start(),
+            None => "",
+        };
+        snippet.push(Text::Plain(format!("…{}", cut)));
+    } else if !before.is_empty() {
+        snippet.push(Text::Plain(before));
+    }
+
+    let mut budget = context;
+    for (index, part) in text.into_iter().enumerate().skip(first_highlight) {
+        let (part_text, is_highlight) = match part {
+            Text::Plain(text) => (text.replace('\n', " "), false),
+            Text::Highlight(text) => (text.replace('\n', " "), true),
+        };
+        let to_text = |text: String| {
+            if is_highlight {
+                Text::Highlight(text)
             } else {
-                result.push(Text::Plain(word));
+                Text::Plain(text)
             }
-            while !target.is_empty() && !target.chars().peekable().peek().unwrap().is_alphanumeric()
-            {
-                result.push(Text::Plain(target.chars().next().unwrap().to_string()));
-                target = target.chars().skip(1).collect();
+        };
+        let length = part_text.chars().count();
+        if index == first_highlight {
+            snippet.push(to_text(part_text));
+            continue;
+        }
+        if length <= budget {
+            budget -= length;
+            snippet.push(to_text(part_text));
+            continue;
+        }
+        // one character more to see whether the last word fits whole
+        let cut: String = part_text.chars().take(budget + 1).collect();
+        if let Some(index) = cut.rfind(char::is_whitespace) {
+            if index > 0 {
+                snippet.push(to_text(cut[..index].trim_end().to_string()));
             }
         }
-        result
+        snippet.push(Text::Plain("…".to_string()));
+        break;
+    }
+    snippet
+}
+
+/// Splits the text into highlighted and plain parts, the ranges may overlap.
+fn split_highlights(target: String, mut ranges: Vec<Range<usize>>) -> Vec<Text> {
+    ranges.sort_by_key(|range| range.start);
+    let mut result = Vec::new();
+    let mut last_end = 0;
+    for range in ranges {
+        if range.end <= last_end {
+            continue;
+        }
+        let start = range.start.max(last_end);
+        if start > last_end {
+            result.push(Text::Plain(target[last_end..start].to_string()));
+        }
+        result.push(Text::Highlight(target[start..range.end].to_string()));
+        last_end = range.end;
+    }
+    if last_end < target.len() {
+        result.push(Text::Plain(target[last_end..].to_string()));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_near_messages, find_regex_spans, idf, make_snippet, parse_date_range, rank_similar,
+        split_highlights, QueryWords, Searcher, Text, ThreadStats, MAX_TREE_DEPTH,
+    };
+    use crate::analysis::deserialization::Message;
+    use crate::analysis::postings::PostingList;
+    use crate::analysis::{Lemmatizer, Segmentation, Tokenizer};
+    use serde_json::{json, Value};
+
+    /// A searcher over the messages, without a lemmatization dictionary.
+    fn searcher(messages: Vec<Value>, segmentation: Segmentation) -> Searcher {
+        let lemmatizer = Box::leak(Box::new(Lemmatizer::default()));
+        let json = json!({ "messages": messages }).to_string();
+        Searcher::new(lemmatizer, Tokenizer::default(), segmentation, json).unwrap()
+    }
+
+    fn message(id: u32, date_unixtime: u32, text: &str, reply_to: Option<u32>) -> Value {
+        message_with_entities(
+            id,
+            date_unixtime,
+            json!([{ "type": "plain", "text": text }]),
+            reply_to,
+        )
+    }
+
+    fn message_with_entities(
+        id: u32,
+        date_unixtime: u32,
+        text_entities: Value,
+        reply_to: Option<u32>,
+    ) -> Value {
+        json!({
+            "id": id,
+            "type": "message",
+            "date_unixtime": date_unixtime.to_string(),
+            "from": "User1",
+            "text_entities": text_entities,
+            "reply_to_message_id": reply_to,
+        })
+    }
+
+    fn found_count(searcher: &Searcher, query: &str) -> usize {
+        searcher.find_thread_ids(query.to_string()).unwrap().len()
+    }
+
+    #[test]
+    fn test_near_counts_words_of_links() {
+        let text_entities = json!([
+            { "type": "plain", "text": "deploy " },
+            { "type": "link", "text": "https://example.com/some/path" },
+            { "type": "plain", "text": " release" },
+        ]);
+        let searcher = searcher(
+            vec![message_with_entities(1, 0, text_entities, None)],
+            Segmentation::default(),
+        );
+        // the link is five words long
+        assert_eq!(0, found_count(&searcher, "deploy NEAR/5 release"));
+        assert_eq!(1, found_count(&searcher, "deploy NEAR/6 release"));
+    }
+
+    #[test]
+    fn test_from_field() {
+        let mut from_ivan = message(2, 0, "deploy", None);
+        from_ivan["from"] = json!("Ivan Petrov");
+        let searcher = searcher(
+            vec![message(1, 0, "deploy", None), from_ivan],
+            Segmentation::default(),
+        );
+        assert_eq!(1, found_count(&searcher, "from:ivan"));
+        assert_eq!(1, found_count(&searcher, "from:\"ivan petrov\" deploy"));
+        assert_eq!(2, found_count(&searcher, "from:user1 | from:petrov"));
+        assert_eq!(0, found_count(&searcher, "from:bob"));
+    }
+
+    fn completed_queries(searcher: &Searcher, query: &str) -> Vec<String> {
+        searcher
+            .complete(query.to_string())
+            .into_iter()
+            .map(|completion| completion.query)
+            .collect()
+    }
+
+    #[test]
+    fn test_complete() {
+        let searcher = searcher(
+            vec![
+                message(1, 0, "deploy", None),
+                message(2, 0, "deploy", None),
+                message(3, 0, "deployment", None),
+            ],
+            Segmentation::default(),
+        );
+        // the term found in more threads comes first
+        assert_eq!(
+            vec!["deploy", "deployment"],
+            completed_queries(&searcher, "depl")
+        );
+        assert_eq!(
+            vec!["release -deploy", "release -deployment"],
+            completed_queries(&searcher, "release -depl")
+        );
+        assert_eq!(
+            vec!["(=deployment"],
+            completed_queries(&searcher, "(=deploym")
+        );
+        // the word itself is not offered
+        assert_eq!(vec!["deployment"], completed_queries(&searcher, "deploy"));
+        assert_eq!(
+            vec!["deploy date:"],
+            completed_queries(&searcher, "deploy da")
+        );
+        // a single letter completes only field names
+        assert_eq!(
+            vec!["underline:", "url:"],
+            completed_queries(&searcher, "u")
+        );
+        assert!(completed_queries(&searcher, "x").is_empty());
+    }
+
+    #[test]
+    fn test_complete_sender() {
+        let mut from_ivan = message(2, 0, "deploy", None);
+        from_ivan["from"] = json!("Ivan Petrov");
+        let searcher = searcher(
+            vec![message(1, 0, "deploy", None), from_ivan],
+            Segmentation::default(),
+        );
+        assert_eq!(
+            vec!["deploy from:\"Ivan Petrov\" "],
+            completed_queries(&searcher, "deploy from:pet")
+        );
+        // a space inside of the quotes doesn't start a new word
+        assert_eq!(
+            vec!["from:\"Ivan Petrov\" "],
+            completed_queries(&searcher, "from:\"ivan p")
+        );
+        assert_eq!(vec!["from:User1 "], completed_queries(&searcher, "from:us"));
+        assert!(completed_queries(&searcher, "code:dep").is_empty());
+    }
+
+    #[test]
+    fn test_date_field() {
+        // 2024-05-10 and 2024-06-01 UTC
+        let searcher = searcher(
+            vec![
+                message(1, 1_715_299_200, "deploy", None),
+                message(2, 1_717_200_000, "deploy", None),
+            ],
+            Segmentation::default(),
+        );
+        assert_eq!(2, found_count(&searcher, "date:2024"));
+        assert_eq!(1, found_count(&searcher, "date:2024-05"));
+        assert_eq!(1, found_count(&searcher, "date:2024-06-01 deploy"));
+        assert_eq!(0, found_count(&searcher, "date:2024-05-11"));
+        assert!(searcher
+            .find_thread_ids("date:2024-13".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_errors_after_empty_operand() {
+        let searcher = searcher(
+            vec![message(1, 1_715_299_200, "deploy", None)],
+            Segmentation::default(),
+        );
+        assert_eq!(0, found_count(&searcher, "release"));
+        for (query, message) in [
+            ("release date:2024-13", "Invalid date 2024-13"),
+            ("release (deploy | date:2024-13)", "Invalid date 2024-13"),
+            ("release -date:2024-13", "Invalid date 2024-13"),
+            ("release (deploy NEAR/3 =deploy)", "NEAR can only combine"),
+        ] {
+            let error = searcher.find_thread_ids(query.to_string()).unwrap_err();
+            assert!(error.message.starts_with(message), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_deep_reply_chain() {
+        let messages = (1..=10_000)
+            .map(|id| message(id, 1_715_299_200 + id, "deploy", (id > 1).then(|| id - 1)))
+            .collect();
+        let searcher = searcher(messages, Segmentation::default());
+        let roots = searcher.get_thread_tree(0, &QueryWords::default());
+        assert_eq!(1, roots.len());
+
+        let mut level = &roots;
+        for depth in 0..MAX_TREE_DEPTH {
+            assert_eq!(1, level.len());
+            assert_eq!(depth, level[0].message.message_id);
+            level = &level[0].replies;
+        }
+        let flattened: Vec<usize> = level.iter().map(|tree| tree.message.message_id).collect();
+        assert_eq!((MAX_TREE_DEPTH..10_000).collect::<Vec<_>>(), flattened);
+        assert!(level.iter().all(|tree| tree.replies.is_empty()));
+    }
+
+    #[test]
+    fn test_reply_to_old_message_is_not_chained() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            ..Segmentation::default()
+        };
+        let day = 24 * 60 * 60;
+        let searcher = searcher(
+            vec![
+                message(1, 1_715_299_200, "deploy", None),
+                message(2, 1_715_299_200 + day, "release", None),
+                message(3, 1_715_299_200 + day + 60, "rollback", Some(1)),
+                message(4, 1_715_299_200 + day + 120, "hotfix", None),
+            ],
+            segmentation,
+        );
+        assert_eq!(1, found_count(&searcher, "deploy rollback"));
+        assert_eq!(0, found_count(&searcher, "release rollback"));
+        assert_eq!(0, found_count(&searcher, "rollback hotfix"));
+        assert_eq!(0, found_count(&searcher, "deploy release"));
+        assert_eq!(1, found_count(&searcher, "release hotfix"));
+    }
+
+    #[test]
+    fn test_conversation_goes_on_over_replies() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            ..Segmentation::default()
+        };
+        let searcher = searcher(
+            vec![
+                message(1, 1_715_299_200, "deploy", None),
+                message(2, 1_715_299_260, "release", None),
+                message(3, 1_715_299_320, "rollback", Some(1)),
+                message(4, 1_715_299_380, "hotfix", None),
+            ],
+            segmentation,
+        );
+        assert_eq!(1, found_count(&searcher, "release hotfix"));
+        assert_eq!(1, found_count(&searcher, "deploy rollback hotfix"));
+    }
+
+    #[test]
+    fn test_split_reply_keeps_own_thread() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            sender_alternation: false,
+            split_threads: true,
+        };
+        let day = 24 * 60 * 60;
+        let searcher = searcher(
+            vec![
+                message(1, 1_715_299_200, "deploy", None),
+                message(2, 1_715_299_200 + 8 * day, "release", None),
+                message(3, 1_715_299_200 + 8 * day + 60, "rollback", Some(1)),
+                message(4, 1_715_299_200 + 8 * day + 120, "hotfix", None),
+                message(5, 1_715_299_200 + 8 * day + 180, "rollback", Some(3)),
+            ],
+            segmentation,
+        );
+        assert_eq!(0, found_count(&searcher, "deploy rollback"));
+        assert_eq!(0, found_count(&searcher, "release rollback"));
+        assert_eq!(0, found_count(&searcher, "rollback hotfix"));
+        assert_eq!(1, found_count(&searcher, "rollback"));
+        assert_eq!(1, found_count(&searcher, "deploy"));
+    }
+
+    #[test]
+    fn test_find_message_by_date() {
+        // 2024-05-10 00:00, 2024-05-10 22:00 and 2024-05-12 12:00 UTC
+        let mut searcher = searcher(
+            vec![
+                message(1, 1_715_299_200, "deploy", None),
+                message(2, 1_715_378_400, "release", None),
+                message(3, 1_715_515_200, "hotfix", None),
+            ],
+            Segmentation::default(),
+        );
+        assert_eq!(Some(0), searcher.find_message_by_date("2024-01-01"));
+        assert_eq!(Some(0), searcher.find_message_by_date("2024-05-10"));
+        assert_eq!(Some(2), searcher.find_message_by_date("2024-05-11"));
+        assert_eq!(Some(2), searcher.find_message_by_date("2025-01-01"));
+        assert_eq!(None, searcher.find_message_by_date("2024-13-01"));
+        assert_eq!(None, searcher.find_message_by_date("yesterday"));
+
+        // the local day of UTC+3 begins at 21:00 UTC of the day before
+        searcher.set_utc_offset(|_| 3 * 60 * 60);
+        assert_eq!(Some(1), searcher.find_message_by_date("2024-05-11"));
+        // and of UTC-5 at 05:00 UTC
+        searcher.set_utc_offset(|_| -5 * 60 * 60);
+        assert_eq!(Some(1), searcher.find_message_by_date("2024-05-10"));
+    }
+
+    #[test]
+    fn test_local_date_field() {
+        // 2024-05-10 22:00 and 2024-05-11 12:00 UTC
+        let mut searcher = searcher(
+            vec![
+                message(1, 1_715_378_400, "deploy", None),
+                message(2, 1_715_428_800, "release", None),
+            ],
+            Segmentation::default(),
+        );
+        assert_eq!(1, found_count(&searcher, "date:2024-05-11"));
+        searcher.set_utc_offset(|_| 3 * 60 * 60);
+        assert_eq!(2, found_count(&searcher, "date:2024-05-11"));
+        assert_eq!(0, found_count(&searcher, "date:2024-05-10"));
+    }
+
+    #[test]
+    fn test_field_queries() {
+        let text_entities = json!([
+            { "type": "plain", "text": "see " },
+            { "type": "text_link", "text": "this PR", "href": "https://github.com/x" },
+            { "type": "plain", "text": " and call " },
+            { "type": "code", "text": "unwrap()" },
+        ]);
+        let searcher = searcher(
+            vec![message_with_entities(1, 1_715_299_200, text_entities, None)],
+            Segmentation::default(),
+        );
+        assert_eq!(1, found_count(&searcher, "link:github.com"));
+        assert_eq!(1, found_count(&searcher, "link:PR"));
+        assert_eq!(1, found_count(&searcher, "url:github.com"));
+        assert_eq!(1, found_count(&searcher, "domain:github.com"));
+        assert_eq!(1, found_count(&searcher, "code:unwrap"));
+        assert_eq!(0, found_count(&searcher, "code:github"));
+        assert_eq!(0, found_count(&searcher, "mention:github"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let searcher = searcher(
+            vec![
+                message(1, 0, "deploy", None),
+                message(2, 0, "deploy release", None),
+            ],
+            Segmentation::default(),
+        );
+        let suggest = |query: &str| searcher.suggest(query.to_string());
+        assert_eq!(Some("deploy".to_string()), suggest("deplyo"));
+        assert_eq!(
+            Some("deploy -release".to_string()),
+            suggest("deplo -relase")
+        );
+        assert_eq!(None, suggest("deploy release"));
+        assert_eq!(None, suggest("deplo*"));
+        assert_eq!(None, suggest("from:deplo"));
+        assert_eq!(None, suggest("/deplo/"));
+        assert_eq!(
+            Some("deploy /a\\/deplo/ NEAR/3 release".to_string()),
+            suggest("deplo /a\\/deplo/ NEAR/3 release")
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let searcher = searcher(
+            vec![
+                message(1, 0, "deploy", None),
+                message(2, 0, "deploys", None),
+                message(3, 0, "release", None),
+            ],
+            Segmentation::default(),
+        );
+        assert_eq!(0, found_count(&searcher, "deplpy"));
+        assert_eq!(2, found_count(&searcher, "deplpy~"));
+        assert_eq!(1, found_count(&searcher, "relase~"));
+        assert_eq!(1, found_count(&searcher, "relase~ | xyzzyq~"));
+        assert_eq!(0, found_count(&searcher, "deplpy~ relase~"));
+    }
+
+    #[test]
+    fn test_find_regex_spans() {
+        assert_eq!(vec![7..13], find_regex_spans("deploy /a\\/b/ c"));
+        assert_eq!(vec![0..3, 4..7], find_regex_spans("/a/ /b/"));
+        assert!(find_regex_spans("a NEAR/3 b").is_empty());
+        assert_eq!(vec![2..5], find_regex_spans("a /bc"));
+    }
+
+    #[test]
+    fn test_split_highlights() {
+        let text = "ticket JIRA-12 fixed".to_string();
+        let output = split_highlights(text, vec![12..14, 7..14, 15..20]);
+        let expected = vec![
+            Text::Plain("ticket ".to_string()),
+            Text::Highlight("JIRA-12".to_string()),
+            Text::Plain(" ".to_string()),
+            Text::Highlight("fixed".to_string()),
+        ];
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_make_snippet() {
+        let plain = |text: &str| Text::Plain(text.to_string());
+        let highlight = |text: &str| Text::Highlight(text.to_string());
+        let text = vec![
+            plain("so after a long discussion\nwe decided to "),
+            highlight("deploy"),
+            plain(" on friday and then "),
+            highlight("deploy"),
+            plain(" again on monday"),
+        ];
+        assert_eq!(
+            vec![
+                plain("…long discussion we decided to "),
+                highlight("deploy"),
+                plain(" on friday and then "),
+                highlight("deploy"),
+                plain(" again"),
+                plain("…"),
+            ],
+            make_snippet(text, 32)
+        );
+        assert_eq!(
+            vec![plain("a "), highlight("b"), plain(" c")],
+            make_snippet(vec![plain("a "), highlight("b"), plain(" c")], 10)
+        );
+        assert!(make_snippet(vec![plain("nothing")], 10).is_empty());
+    }
+
+    #[test]
+    fn test_rank_similar() {
+        // thread 0: deploy friday, 1: deploy friday monday, 2: deploy, 3..8: lunch,
+        // every thread also has "chat"
+        let deploy: PostingList = vec![0, 1, 2].into_iter().collect();
+        let friday: PostingList = vec![0, 1].into_iter().collect();
+        let monday: PostingList = vec![1].into_iter().collect();
+        let lunch: PostingList = (3..8).collect();
+        let chat: PostingList = (0..8).collect();
+        let weight = |posting_list: &PostingList| idf(8, posting_list.len()).powi(2);
+        let norms: Vec<f32> = vec![
+            weight(&deploy) + weight(&friday),
+            weight(&deploy) + weight(&friday) + weight(&monday),
+            weight(&deploy),
+        ]
+        .into_iter()
+        .chain((3..8).map(|_| weight(&lunch)))
+        .map(f32::sqrt)
+        .collect();
+        let similar = rank_similar(&[&deploy, &friday, &chat], &norms, 0);
+        let ids: Vec<usize> = similar.iter().map(|(thread_id, _)| *thread_id).collect();
+        assert_eq!(vec![1, 2], ids);
+        assert!(similar[0].1 > similar[1].1 && similar[0].1 <= 1.0);
+        assert!(rank_similar(&[&chat], &norms, 0).is_empty());
+    }
+
+    #[test]
+    fn test_thread_stats() {
+        let message = |id: usize, from: &str, reply_to_message_id: Option<usize>| Message {
+            id,
+            text_entities: Vec::new(),
+            date_unixtime: id as u32,
+            from: Some(from.to_string()),
+            reply_to_message_id,
+        };
+        let messages = vec![
+            message(0, "bob", None),
+            message(1, "alice", Some(0)),
+            message(2, "carol", None),
+            message(3, "alice", Some(1)),
+            message(4, "bob", Some(0)),
+        ];
+        let stats = ThreadStats::new(&[0, 1, 3, 4], &messages);
+        assert_eq!(vec!["alice", "bob"], stats.participants);
+        assert_eq!(2, stats.reply_depth);
+    }
+
+    #[test]
+    fn test_parse_date_range() {
+        assert_eq!(Some((1704067200, 1735689600)), parse_date_range("2024"));
+        assert_eq!(Some((1733011200, 1735689600)), parse_date_range("2024-12"));
+        assert_eq!(
+            Some((1709164800, 1709251200)),
+            parse_date_range("2024-02-29")
+        );
+        assert_eq!(None, parse_date_range("2023-02-29"));
+        assert_eq!(None, parse_date_range("2024-13"));
+        assert_eq!(None, parse_date_range("yesterday"));
+    }
+
+    #[test]
+    fn test_find_near_messages() {
+        let left = vec![(0, 1), (0, 10), (2, 0), (3, 7)];
+        let right = vec![(0, 4), (1, 1), (2, 9), (3, 3), (3, 8)];
+        assert_eq!(vec![0, 3], find_near_messages(&left, &right, 3));
+        assert_eq!(vec![3], find_near_messages(&left, &right, 1));
+        assert_eq!(vec![0, 2, 3], find_near_messages(&left, &right, 9));
     }
 }
@@ -0,0 +1,134 @@
+use crate::analysis::deserialization::Message;
+
+/// Messages of one sender this close to each other are a single burst even when a change
+/// of the sender is required to continue a conversation.
+const BURST_GAP_SECONDS: u32 = 60;
+
+/// A reply to a message older than this starts a sub-thread of its own.
+const SPLIT_REPLY_GAP_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+/// Past this many messages a thread is split at any reply that comes
+/// `LARGE_THREAD_REPLY_GAP_SECONDS` after its parent.
+const MAX_THREAD_MESSAGES: usize = 300;
+const LARGE_THREAD_REPLY_GAP_SECONDS: u32 = 6 * 60 * 60;
+
+/// How consecutive messages that are not replies to each other are grouped into
+/// conversations, in addition to the reply threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Segmentation {
+    /// Messages at most this many minutes apart belong to one conversation,
+    /// `None` keeps the reply threads only
+    pub max_gap_minutes: Option<u32>,
+    /// A message continues the conversation only if it comes from another sender than
+    /// the previous message, or right after it, so that a new post after a pause starts
+    /// a new conversation
+    pub sender_alternation: bool,
+    /// Replies to old messages start sub-threads instead of gluing months of discussion
+    /// into a single thread
+    pub split_threads: bool,
+}
+
+impl Segmentation {
+    /// Whether `message` continues the conversation of `previous`, the last message before
+    /// it that is not a reply. Replies are left to their own threads, chaining them by time
+    /// would glue the thread they reply to onto the messages around them.
+    pub fn continues(&self, previous: &Message, message: &Message) -> bool {
+        let max_gap_minutes = match self.max_gap_minutes {
+            Some(max_gap_minutes) => max_gap_minutes,
+            None => return false,
+        };
+        if previous.reply_to_message_id.is_some() || message.reply_to_message_id.is_some() {
+            return false;
+        }
+        let gap = message.date_unixtime.saturating_sub(previous.date_unixtime);
+        if gap > max_gap_minutes * 60 {
+            return false;
+        }
+        !self.sender_alternation || previous.from != message.from || gap <= BURST_GAP_SECONDS
+    }
+
+    /// Whether `reply` joins the thread of the message it replies to, `size` is how many
+    /// messages the two threads would have together.
+    pub fn keeps_reply(&self, parent: &Message, reply: &Message, size: usize) -> bool {
+        if !self.split_threads {
+            return true;
+        }
+        let gap = reply.date_unixtime.saturating_sub(parent.date_unixtime);
+        gap <= SPLIT_REPLY_GAP_SECONDS
+            && (size <= MAX_THREAD_MESSAGES || gap <= LARGE_THREAD_REPLY_GAP_SECONDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Segmentation;
+    use crate::analysis::deserialization::Message;
+
+    fn message(minute: u32, from: &str) -> Message {
+        Message {
+            id: 0,
+            text_entities: Vec::new(),
+            date_unixtime: minute * 60,
+            from: Some(from.to_string()),
+            reply_to_message_id: None,
+        }
+    }
+
+    #[test]
+    fn test_time_gap() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            sender_alternation: false,
+            split_threads: false,
+        };
+        assert!(segmentation.continues(&message(0, "a"), &message(10, "a")));
+        assert!(!segmentation.continues(&message(0, "a"), &message(11, "b")));
+        assert!(!Segmentation::default().continues(&message(0, "a"), &message(0, "b")));
+    }
+
+    #[test]
+    fn test_replies_are_not_chained() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            sender_alternation: false,
+            split_threads: false,
+        };
+        let reply = Message {
+            reply_to_message_id: Some(0),
+            ..message(5, "b")
+        };
+        assert!(!segmentation.continues(&message(0, "a"), &reply));
+        assert!(!segmentation.continues(&reply, &message(10, "a")));
+    }
+
+    #[test]
+    fn test_sender_alternation() {
+        let segmentation = Segmentation {
+            max_gap_minutes: Some(10),
+            sender_alternation: true,
+            split_threads: false,
+        };
+        assert!(segmentation.continues(&message(0, "a"), &message(5, "b")));
+        assert!(segmentation.continues(&message(0, "a"), &message(1, "a")));
+        assert!(!segmentation.continues(&message(0, "a"), &message(5, "a")));
+    }
+
+    #[test]
+    fn test_split_threads() {
+        let segmentation = Segmentation {
+            split_threads: true,
+            ..Segmentation::default()
+        };
+        let day = 24 * 60;
+        assert!(segmentation.keeps_reply(&message(0, "a"), &message(7 * day, "b"), 2));
+        assert!(!segmentation.keeps_reply(&message(0, "a"), &message(8 * day, "b"), 2));
+        assert!(segmentation.keeps_reply(&message(0, "a"), &message(day, "b"), 300));
+        assert!(!segmentation.keeps_reply(&message(0, "a"), &message(day, "b"), 301));
+        assert!(segmentation.keeps_reply(&message(0, "a"), &message(60, "b"), 1000));
+        assert!(Segmentation::default().keeps_reply(
+            &message(0, "a"),
+            &message(100 * day, "b"),
+            1000
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::analysis::lemmatizer::Lemmatizer;
+use crate::analysis::tokenizer::normalize;
+use std::collections::HashMap;
+
+/// User-defined groups of interchangeable words, stored as lemmas.
+///
+/// Each non-empty line of the source text is one group, with words separated by `=`,
+/// e.g. `прод = продакшн = prod`.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Synonyms {
+    groups: Vec<Vec<String>>,
+    group_by_lemma: HashMap<String, usize>,
+}
+
+impl Synonyms {
+    pub fn parse(text: &str, lemmatizer: &Lemmatizer) -> Self {
+        let mut synonyms = Synonyms::default();
+        for line in text.lines() {
+            let mut group: Vec<String> = Vec::new();
+            for word in line.split('=') {
+                let word = normalize(word.trim());
+                if word.is_empty() {
+                    continue;
+                }
+                let lemma = lemmatizer.lemmatize(&word).to_string();
+                if !group.contains(&lemma) {
+                    group.push(lemma);
+                }
+            }
+            if group.len() > 1 {
+                synonyms.add_group(group);
+            }
+        }
+        synonyms
+    }
+
+    fn add_group(&mut self, group: Vec<String>) {
+        // a lemma already present in another group joins the two groups together
+        let existing = group
+            .iter()
+            .find_map(|lemma| self.group_by_lemma.get(lemma).copied());
+        let group_id = existing.unwrap_or_else(|| {
+            self.groups.push(Vec::new());
+            self.groups.len() - 1
+        });
+        for lemma in group {
+            match self.group_by_lemma.get(&lemma).copied() {
+                Some(id) if id == group_id => {}
+                Some(id) => {
+                    let merged = std::mem::take(&mut self.groups[id]);
+                    for merged_lemma in merged {
+                        self.group_by_lemma.insert(merged_lemma.clone(), group_id);
+                        self.groups[group_id].push(merged_lemma);
+                    }
+                }
+                None => {
+                    self.group_by_lemma.insert(lemma.clone(), group_id);
+                    self.groups[group_id].push(lemma);
+                }
+            }
+        }
+    }
+
+    /// Returns the lemma itself followed by all of its synonyms.
+    pub fn expand(&self, lemma: &str) -> Vec<String> {
+        match self.group_by_lemma.get(lemma) {
+            Some(&group_id) => {
+                let mut result = vec![lemma.to_string()];
+                result.extend(
+                    self.groups[group_id]
+                        .iter()
+                        .filter(|synonym| *synonym != lemma)
+                        .cloned(),
+                );
+                result
+            }
+            None => vec![lemma.to_string()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Synonyms;
+    use crate::analysis::lemmatizer::Lemmatizer;
+
+    #[test]
+    fn test_expand() {
+        let lemmatizer = Lemmatizer::default();
+        let synonyms = Synonyms::parse("прод = Продакшн = prod\n\nbug = баг", &lemmatizer);
+        assert_eq!(
+            vec!["продакшн", "прод", "prod"],
+            synonyms.expand("продакшн")
+        );
+        assert_eq!(vec!["баг", "bug"], synonyms.expand("баг"));
+        assert_eq!(vec!["деплой"], synonyms.expand("деплой"));
+    }
+
+    #[test]
+    fn test_overlapping_groups_are_merged() {
+        let lemmatizer = Lemmatizer::default();
+        let synonyms = Synonyms::parse("a = b\nc = d\nb = c", &lemmatizer);
+        assert_eq!(vec!["d", "a", "b", "c"], synonyms.expand("d"));
+    }
+
+    #[test]
+    fn test_single_word_lines_are_ignored() {
+        let lemmatizer = Lemmatizer::default();
+        let synonyms = Synonyms::parse("a\n = b =", &lemmatizer);
+        assert_eq!(Synonyms::default(), synonyms);
+    }
+}
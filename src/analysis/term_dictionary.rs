@@ -0,0 +1,122 @@
+use crate::analysis::bk_tree::BkTree;
+use crate::analysis::utils;
+use std::sync::OnceLock;
+
+/// Checks a term against a pattern where `*` stands for any sequence of characters.
+fn matches_wildcard(pattern: &[char], term: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut last_star = None;
+    let mut star_match = 0;
+    while t < term.len() {
+        if p < pattern.len() && pattern[p] != '*' && pattern[p] == term[t] {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some(p);
+            star_match = t;
+            p += 1;
+        } else if let Some(star) = last_star {
+            p = star + 1;
+            star_match += 1;
+            t = star_match;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// All the terms of the index, sorted, for lookups that don't know the exact term.
+#[derive(Default)]
+pub struct TermDictionary {
+    terms: Vec<String>,
+    /// Built on the first fuzzy lookup, most chats are indexed again before one happens
+    bk_tree: OnceLock<BkTree>,
+}
+
+impl TermDictionary {
+    pub fn new(mut terms: Vec<String>) -> Self {
+        terms.sort();
+        terms.dedup();
+        TermDictionary {
+            terms,
+            bk_tree: OnceLock::new(),
+        }
+    }
+
+    /// Returns all terms within `max_distance` of `word` together with their distances.
+    pub fn find_similar(&self, word: &str, max_distance: usize) -> Vec<(usize, &str)> {
+        let bk_tree = self.bk_tree.get_or_init(|| {
+            let time_start = chrono::Utc::now();
+            let mut bk_tree = BkTree::new();
+            for term in &self.terms {
+                bk_tree.insert(term.clone());
+            }
+            utils::log!(
+                "Building BK-tree of {} terms took {:?}",
+                self.terms.len(),
+                chrono::Utc::now() - time_start
+            );
+            bk_tree
+        });
+        bk_tree.find(word, max_distance)
+    }
+
+    /// Returns all terms matching a normalized pattern such as `deplo*` or `*config*`.
+    pub fn find_wildcard(&self, pattern: &str) -> Vec<&str> {
+        let prefix = pattern.split('*').next().unwrap_or_default();
+        let start = self.terms.partition_point(|term| term.as_str() < prefix);
+        let pattern: Vec<char> = pattern.chars().collect();
+        self.terms[start..]
+            .iter()
+            .take_while(|term| term.starts_with(prefix))
+            .filter(|term| matches_wildcard(&pattern, &term.chars().collect::<Vec<_>>()))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermDictionary;
+
+    fn dictionary() -> TermDictionary {
+        TermDictionary::new(
+            [
+                "deploy",
+                "deployment",
+                "config",
+                "reconfigure",
+                "dep",
+                "configs",
+                "deploy",
+            ]
+            .iter()
+            .map(|term| term.to_string())
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_prefix() {
+        assert_eq!(
+            vec!["deploy", "deployment"],
+            dictionary().find_wildcard("deplo*")
+        );
+    }
+
+    #[test]
+    fn test_infix() {
+        assert_eq!(
+            vec!["config", "configs", "reconfigure"],
+            dictionary().find_wildcard("*config*")
+        );
+        assert_eq!(vec!["config"], dictionary().find_wildcard("*fig"));
+        assert_eq!(vec!["deployment"], dictionary().find_wildcard("d*p*t"));
+    }
+
+    #[test]
+    fn test_no_wildcard_is_exact_match() {
+        assert_eq!(vec!["dep"], dictionary().find_wildcard("dep"));
+    }
+}
@@ -1,43 +1,95 @@
-use std::collections::HashMap;
-
+/// Disjoint sets of message ids, one set per thread, with union by size and path compression.
 pub struct ThreadDSU {
-    threads: HashMap<usize, Vec<usize>>, // thread_id -> message_ids
-    parents: HashMap<usize, usize>,
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
 }
 
 impl ThreadDSU {
-    pub fn new() -> Self {
+    /// Every message from `0` to `len - 1` starts in a thread of its own.
+    pub fn new(len: usize) -> Self {
         Self {
-            threads: HashMap::new(),
-            parents: HashMap::new(),
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
         }
     }
 
-    pub fn make_set(&mut self, v: usize) {
-        self.parents.insert(v, v);
-        self.threads.insert(v, vec![v]);
+    pub fn find_set(&mut self, v: usize) -> usize {
+        let mut root = v;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut v = v;
+        while self.parents[v] != root {
+            let parent = self.parents[v];
+            self.parents[v] = root;
+            v = parent;
+        }
+        root
     }
 
-    pub fn find_set(&self, v: usize) -> Option<usize> {
-        self.parents.get(&v).copied()
+    /// How many messages are in the thread of `v`.
+    pub fn set_size(&mut self, v: usize) -> usize {
+        let root = self.find_set(v);
+        self.sizes[root]
     }
 
     pub fn union_sets(&mut self, a: usize, b: usize) {
-        if let Some(a) = self.find_set(a) {
-            if let Some(b) = self.find_set(b) {
-                if a != b {
-                    let thread_b = self.threads.remove(&b).unwrap();
-                    for v in &thread_b {
-                        self.parents.insert(*v, a);
-                    }
-                    self.threads.get_mut(&a).unwrap().extend(thread_b);
-                }
-            }
+        let a = self.find_set(a);
+        let b = self.find_set(b);
+        if a == b {
+            return;
         }
+        let (larger, smaller) = if self.sizes[a] >= self.sizes[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parents[smaller] = larger;
+        self.sizes[larger] += self.sizes[smaller];
     }
 
-    pub fn get_threads(&self) -> Vec<Vec<usize>> {
-        // each Vec<usize> is one thread
-        self.threads.values().cloned().collect()
+    /// The threads ordered by their first message, with the message ids of each thread sorted,
+    /// so the thread ids are the same for the same chat.
+    pub fn get_threads(&mut self) -> Vec<Vec<usize>> {
+        let mut thread_by_root = vec![None; self.parents.len()];
+        let mut threads: Vec<Vec<usize>> = Vec::new();
+        for v in 0..self.parents.len() {
+            let root = self.find_set(v);
+            let thread_id = *thread_by_root[root].get_or_insert_with(|| {
+                threads.push(Vec::new());
+                threads.len() - 1
+            });
+            threads[thread_id].push(v);
+        }
+        threads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadDSU;
+
+    #[test]
+    fn test_get_threads() {
+        let mut dsu = ThreadDSU::new(7);
+        dsu.union_sets(5, 6);
+        dsu.union_sets(1, 5);
+        dsu.union_sets(3, 0);
+        dsu.union_sets(6, 1);
+        assert_eq!(
+            vec![vec![0, 3], vec![1, 5, 6], vec![2], vec![4]],
+            dsu.get_threads()
+        );
+    }
+
+    #[test]
+    fn test_long_chain() {
+        let len = 100_000;
+        let mut dsu = ThreadDSU::new(len);
+        for v in 1..len {
+            dsu.union_sets(v - 1, v);
+        }
+        assert_eq!(dsu.find_set(0), dsu.find_set(len - 1));
+        assert_eq!(vec![(0..len).collect::<Vec<_>>()], dsu.get_threads());
     }
 }
@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+const RUSSIAN_STOPWORDS: &[&str] = &[
+    "и",
+    "в",
+    "во",
+    "не",
+    "что",
+    "он",
+    "на",
+    "я",
+    "с",
+    "со",
+    "как",
+    "а",
+    "то",
+    "все",
+    "она",
+    "так",
+    "его",
+    "но",
+    "да",
+    "ты",
+    "к",
+    "у",
+    "же",
+    "вы",
+    "за",
+    "бы",
+    "по",
+    "только",
+    "ее",
+    "мне",
+    "было",
+    "вот",
+    "от",
+    "меня",
+    "еще",
+    "нет",
+    "о",
+    "из",
+    "ему",
+    "теперь",
+    "когда",
+    "даже",
+    "ну",
+    "вдруг",
+    "ли",
+    "если",
+    "уже",
+    "или",
+    "ни",
+    "быть",
+    "был",
+    "него",
+    "до",
+    "вас",
+    "нибудь",
+    "опять",
+    "уж",
+    "вам",
+    "ведь",
+    "там",
+    "потом",
+    "себя",
+    "ничего",
+    "ей",
+    "может",
+    "они",
+    "тут",
+    "где",
+    "есть",
+    "надо",
+    "ней",
+    "для",
+    "мы",
+    "тебя",
+    "их",
+    "чем",
+    "была",
+    "сам",
+    "чтоб",
+    "без",
+    "будто",
+    "чего",
+    "раз",
+    "тоже",
+    "себе",
+    "под",
+    "будет",
+    "ж",
+    "тогда",
+    "кто",
+    "этот",
+    "того",
+    "потому",
+    "этого",
+    "какой",
+    "совсем",
+    "ним",
+    "здесь",
+    "этом",
+    "один",
+    "почти",
+    "мой",
+    "тем",
+    "чтобы",
+    "нее",
+    "сейчас",
+    "были",
+    "куда",
+    "зачем",
+    "всех",
+    "никогда",
+    "можно",
+    "при",
+    "наконец",
+    "два",
+    "об",
+    "другой",
+    "хоть",
+    "после",
+    "над",
+    "больше",
+    "тот",
+    "через",
+    "эти",
+    "нас",
+    "про",
+    "всего",
+    "них",
+    "какая",
+    "много",
+    "разве",
+    "три",
+    "эту",
+    "моя",
+    "впрочем",
+    "хорошо",
+    "свою",
+    "этой",
+    "перед",
+    "иногда",
+    "лучше",
+    "чуть",
+    "том",
+    "нельзя",
+    "такой",
+    "им",
+    "более",
+    "всегда",
+    "конечно",
+    "всю",
+    "между",
+    "это",
+    "эта",
+    "вообще",
+    "просто",
+    "очень",
+];
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "if", "then", "else", "of", "at", "by", "for", "with",
+    "about", "to", "from", "in", "on", "is", "are", "was", "were", "be", "been", "being", "have",
+    "has", "had", "do", "does", "did", "it", "its", "this", "that", "these", "those", "i", "you",
+    "he", "she", "we", "they", "me", "him", "her", "us", "them", "my", "your", "his", "our",
+    "their", "not", "no", "so", "as", "can", "will", "just", "there", "here", "what", "which",
+    "who", "when", "where", "why", "how", "all", "any", "some", "such", "than", "too", "very",
+    "also", "into", "out", "up", "down",
+];
+
+/// Short tokens that are meaningful in technical chats and should be searchable
+/// even though they are below the default minimum length.
+const DEFAULT_SHORT_TOKENS: &[&str] = &[
+    "ai", "ci", "cd", "db", "go", "js", "ts", "ml", "qa", "ui", "ux", "os", "vm", "pr",
+];
+
+const DEFAULT_MIN_LENGTH: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Russian,
+    English,
+}
+
+impl Language {
+    fn stopwords(self) -> &'static [&'static str] {
+        match self {
+            Language::Russian => RUSSIAN_STOPWORDS,
+            Language::English => ENGLISH_STOPWORDS,
+        }
+    }
+}
+
+/// Characters that glue two words into one token, e.g. "кто-то" or "don't".
+const JOINERS: &[char] = &['-', '\u{2010}', '\u{2011}', '\'', '\u{2019}', '\u{02bc}'];
+
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c)
+}
+
+pub(crate) fn is_joiner(c: char) -> bool {
+    JOINERS.contains(&c)
+}
+
+/// Brings a word to the form it is indexed under: NFKC, lowercase, "ё" folded into "е",
+/// diacritics stripped from latin letters and hyphens/apostrophes unified.
+pub(crate) fn normalize(word: &str) -> String {
+    let mut result = String::with_capacity(word.len());
+    for c in word.nfkc().flat_map(char::to_lowercase) {
+        match c {
+            'ё' => result.push('е'),
+            '\u{2010}' | '\u{2011}' => result.push('-'),
+            '\u{2019}' | '\u{02bc}' => result.push('\''),
+            c if c.is_ascii() => result.push(c),
+            c => {
+                let mut decomposed = Vec::new();
+                decompose_canonical(c, |d| decomposed.push(d));
+                if decomposed[0].is_ascii() {
+                    result.extend(decomposed.into_iter().filter(|d| !is_combining_mark(*d)));
+                } else {
+                    result.push(c);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// A word found in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Byte range of the word in the original text
+    pub span: Range<usize>,
+    /// The normalized word
+    pub text: String,
+}
+
+impl Token {
+    /// The token itself followed by its hyphen-separated parts, if it has any.
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = vec![self.text.clone()];
+        if self.text.contains('-') {
+            terms.extend(
+                self.text
+                    .split('-')
+                    .filter(|part| !part.is_empty())
+                    .map(String::from),
+            );
+        }
+        terms
+    }
+}
+
+/// Decides which words of a text are worth indexing and searching for.
+///
+/// Words are normalized before any checks. A word is kept if it is allowlisted
+/// as a short token, or if it is at least `min_length` characters long and is not a stopword.
+#[derive(Clone, Debug)]
+pub struct Tokenizer {
+    min_length: usize,
+    stopwords: HashSet<String>,
+    short_tokens: HashSet<String>,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Tokenizer::new(
+            DEFAULT_MIN_LENGTH,
+            &[Language::Russian, Language::English],
+            DEFAULT_SHORT_TOKENS,
+        )
+    }
+}
+
+impl Tokenizer {
+    pub fn new(min_length: usize, languages: &[Language], short_tokens: &[&str]) -> Self {
+        Tokenizer {
+            min_length,
+            stopwords: languages
+                .iter()
+                .flat_map(|language| language.stopwords())
+                .map(|word| normalize(word))
+                .collect(),
+            short_tokens: short_tokens.iter().map(|word| normalize(word)).collect(),
+        }
+    }
+
+    /// Expects a normalized word.
+    pub fn is_indexable(&self, word: &str) -> bool {
+        if self.short_tokens.contains(word) {
+            return true;
+        }
+        word.chars().count() >= self.min_length && !self.stopwords.contains(word)
+    }
+
+    /// Splits the text into words. A hyphen or an apostrophe stays inside a word
+    /// when it is surrounded by letters on both sides.
+    pub fn tokenize(text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        let mut chars = text.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            let next_is_word_char =
+                matches!(chars.peek(), Some((_, next)) if next.is_alphanumeric());
+            let continues_word =
+                is_word_char(c) || (start.is_some() && is_joiner(c) && next_is_word_char);
+            match (start, continues_word) {
+                (None, true) if c.is_alphanumeric() => start = Some(index),
+                (Some(word_start), false) => {
+                    tokens.push(Token {
+                        span: word_start..index,
+                        text: normalize(&text[word_start..index]),
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(word_start) = start {
+            tokens.push(Token {
+                span: word_start..text.len(),
+                text: normalize(&text[word_start..]),
+            });
+        }
+        tokens
+    }
+
+    /// Splits the text into normalized words and their hyphen-separated parts,
+    /// dropping the ones that are not indexable.
+    pub fn words<'a>(&'a self, text: &str) -> impl Iterator<Item = String> + 'a {
+        Tokenizer::tokenize(text)
+            .into_iter()
+            .flat_map(|token| token.terms())
+            .filter(move |word| self.is_indexable(word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, Language, Token, Tokenizer};
+
+    #[test]
+    fn test_length_is_counted_in_characters() {
+        let tokenizer = Tokenizer::new(3, &[], &[]);
+        let words: Vec<String> = tokenizer.words("Да, API и SQL: k8s bug").collect();
+        assert_eq!(vec!["api", "sql", "k8s", "bug"], words);
+    }
+
+    #[test]
+    fn test_stopwords() {
+        let tokenizer = Tokenizer::new(3, &[Language::Russian, Language::English], &[]);
+        let words: Vec<String> = tokenizer.words("Это было очень the deploy").collect();
+        assert_eq!(vec!["deploy"], words);
+    }
+
+    #[test]
+    fn test_short_tokens() {
+        let tokenizer = Tokenizer::new(3, &[], &["CI"]);
+        let words: Vec<String> = tokenizer.words("ci is ok").collect();
+        assert_eq!(vec!["ci"], words);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("еще", normalize("ЕЩЁ"));
+        assert_eq!("все", normalize("все\u{0308}"));
+        assert_eq!("cafe", normalize("Café"));
+        assert_eq!("йод", normalize("йод"));
+        assert_eq!("don't", normalize("don\u{2019}t"));
+        assert_eq!("fi", normalize("\u{fb01}"));
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let text = "Всё, кто-то - don't!";
+        let tokens = Tokenizer::tokenize(text);
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(vec!["все", "кто-то", "don't"], texts);
+        assert_eq!("кто-то", &text[tokens[1].span.clone()]);
+        assert_eq!(
+            Token {
+                span: 0..6,
+                text: "все".to_string()
+            },
+            tokens[0]
+        );
+    }
+
+    #[test]
+    fn test_hyphenated_words_are_split_into_parts() {
+        let tokenizer = Tokenizer::new(3, &[], &[]);
+        let words: Vec<String> = tokenizer.words("full-text search-").collect();
+        assert_eq!(vec!["full-text", "full", "text", "search"], words);
+    }
+}
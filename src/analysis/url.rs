@@ -0,0 +1,103 @@
+/// A link split into the parts that can be searched for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    /// The link as it was written, with `https://` added if it had no scheme,
+    /// so it is safe to open
+    pub url: String,
+    /// Lowercased host without `www.` and the port
+    pub domain: String,
+}
+
+/// Splits a link such as `https://www.github.com/rust-lang?tab=repositories` or
+/// `github.com/rust-lang` into its parts. Returns `None` if there is no host or the
+/// scheme is other than `http` and `https`, like `javascript:` or `mailto:`.
+pub fn parse_url(url: &str) -> Option<Url> {
+    let url = url.trim();
+    let (url, without_scheme) = match url.find("://") {
+        Some(index) => {
+            let scheme = url[..index].to_ascii_lowercase();
+            if scheme != "http" && scheme != "https" {
+                return None;
+            }
+            (url.to_string(), &url[index + 3..])
+        }
+        None => {
+            // `mailto:a@b.c`, as opposed to `example.com:8080`
+            let before_colon = url.split(':').next().unwrap_or_default();
+            if url.contains(':') && !before_colon.contains(['.', '/']) {
+                return None;
+            }
+            (format!("https://{}", url), url)
+        }
+    };
+    let authority_end = without_scheme
+        .find(['/', '?', '#'])
+        .unwrap_or(without_scheme.len());
+    let authority = &without_scheme[..authority_end];
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().to_lowercase();
+    let domain = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    if domain.is_empty() {
+        return None;
+    }
+    Some(Url { url, domain })
+}
+
+/// Checks whether the domain is the given one or its subdomain, so that `github.com`
+/// matches `gist.github.com` but not `notgithub.com`.
+pub fn matches_domain(domain: &str, pattern: &str) -> bool {
+    domain == pattern
+        || domain
+            .strip_suffix(pattern)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_domain, parse_url, Url};
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            Some(Url {
+                url: "https://user@www.GitHub.com:443/rust-lang/rust?tab=issues#top".to_string(),
+                domain: "github.com".to_string(),
+            }),
+            parse_url("https://user@www.GitHub.com:443/rust-lang/rust?tab=issues#top")
+        );
+        assert_eq!(
+            Some(Url {
+                url: "https://docs.rs".to_string(),
+                domain: "docs.rs".to_string(),
+            }),
+            parse_url(" docs.rs ")
+        );
+        assert_eq!(None, parse_url("https:///path"));
+    }
+
+    #[test]
+    fn test_unsafe_schemes() {
+        assert_eq!(None, parse_url("javascript:alert(1)"));
+        assert_eq!(None, parse_url("JavaScript://example.com/%0Aalert(1)"));
+        assert_eq!(None, parse_url("mailto:a@b.c"));
+        assert_eq!(None, parse_url("ftp://example.com/file"));
+    }
+
+    #[test]
+    fn test_scheme_less() {
+        let url = parse_url("github.com/x").unwrap();
+        assert_eq!("https://github.com/x", url.url);
+        assert_eq!("github.com", url.domain);
+        let url = parse_url("example.com:8080/x").unwrap();
+        assert_eq!("https://example.com:8080/x", url.url);
+        assert_eq!("example.com", url.domain);
+    }
+
+    #[test]
+    fn test_matches_domain() {
+        assert!(matches_domain("github.com", "github.com"));
+        assert!(matches_domain("gist.github.com", "github.com"));
+        assert!(!matches_domain("notgithub.com", "github.com"));
+        assert!(!matches_domain("github.com", "gist.github.com"));
+    }
+}
@@ -1,7 +1,11 @@
+/// Logs to the browser console. Outside of the browser, in tests, nothing is logged.
 macro_rules! log {
-    ( $( $t:tt )* ) => {
+    ( $( $t:tt )* ) => {{
+        #[cfg(target_arch = "wasm32")]
         web_sys::console::log_1(&format!( $( $t )* ).into());
-    }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = format!( $( $t )* );
+    }}
 }
 
 pub(crate) use log;
@@ -1,2 +1,5 @@
 pub mod file_input;
 pub mod search;
+pub mod segmentation;
+pub mod synonyms;
+pub mod virtual_list;
@@ -1,23 +1,83 @@
 use leptos::either::Either;
 use leptos::logging::log;
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
-use web_sys::MouseEvent;
+use web_sys::{KeyboardEvent, MouseEvent};
 
-use crate::analysis::{MessageResult, ThreadSearchResult};
-use crate::analysis::{Searcher, Text};
+use crate::analysis::{
+    Completion, LinkResult, MessageTree, QueryError, QueryWords, ThreadPage, ThreadSearchResult,
+};
+use crate::analysis::{Searcher, Segmentation, Text};
+use crate::components::segmentation::SegmentationSettings;
+use crate::components::synonyms::{load_synonyms, SynonymsEditor};
+use crate::components::virtual_list::VirtualList;
 use chrono::DateTime;
 use leptos::html;
 use leptos::prelude::*;
 
 #[component]
-pub fn Search(searcher: LocalResource<Option<Arc<Mutex<Searcher>>>>) -> impl IntoView {
+pub fn Search(
+    searcher: LocalResource<Option<Arc<Mutex<Searcher>>>>,
+    segmentation: ReadSignal<Segmentation>,
+    set_segmentation: WriteSignal<Segmentation>,
+) -> impl IntoView {
     let (search_query, set_search_query) = signal(String::new());
+    // what is typed into the search bar but not submitted yet
+    let (draft_query, set_draft_query) = signal(String::new());
     let (selected_thread_id, set_selected_thread_id) = signal(None::<u32>);
-    let (offset_before, set_offset_before) = signal(0usize);
-    let (offset_after, set_offset_after) = signal(0usize);
+    // the flat range of messages around the thread instead of its reply tree
+    let (show_context, set_show_context) = signal(false);
+    // the thread whose related threads are listed instead of the search results
+    let (similar_to, set_similar_to) = signal(None::<u32>);
+    // the whole chat in order instead of the search results
+    let (browse, set_browse) = signal(false);
+    // the rows of the thread list in view
+    let (thread_window, set_thread_window) = signal(0..THREAD_PAGE_SIZE);
+    let (synonyms_text, set_synonyms_text) = signal(load_synonyms());
+
+    // the queries that depend on the synonyms run again once the searcher has them
+    let synonyms_applied = Trigger::new();
+    Effect::new(move |_| {
+        let synonyms_text = synonyms_text.get();
+        if let Some(searcher) = searcher.read().as_deref().flatten() {
+            searcher.lock().unwrap().set_synonyms(&synonyms_text);
+        }
+        synonyms_applied.notify();
+    });
+
+    Effect::new(move |_| set_draft_query.set(search_query.get()));
+
+    // thread ids change whenever the chat is indexed again
+    Effect::new(move |_| {
+        searcher.track();
+        set_selected_thread_id.set(None);
+        set_similar_to.set(None);
+    });
+
+    // the window is reset together with the query, so that the new results are not
+    // summarized for the window of the old ones first
+    let submit_query = Callback::new(move |query: String| {
+        set_similar_to.set(None);
+        set_thread_window.set(0..THREAD_PAGE_SIZE);
+        set_search_query.set(query);
+    });
+
+    let completions = Memo::new(move |_| {
+        let draft_query = draft_query.get();
+        if draft_query.is_empty() {
+            return Vec::new();
+        }
+        searcher
+            .read()
+            .as_deref()
+            .flatten()
+            .map(|searcher| searcher.lock().unwrap().complete(draft_query))
+            .unwrap_or_default()
+    });
 
     let query_words = Memo::new(move |_| {
         log!("getting query words...");
+        synonyms_applied.track();
         if let Some(searcher) = searcher.read().as_deref() {
             searcher
                 .as_ref()
@@ -26,197 +86,814 @@ pub fn Search(searcher: LocalResource<Option<Arc<Mutex<Searcher>>>>) -> impl Int
                 .unwrap()
                 .get_query_words(search_query.get().clone())
         } else {
-            Vec::new()
+            QueryWords::default()
         }
     });
 
-    let result_threads = Memo::new(move |_| {
+    let found_thread_ids = Memo::new(move |_| {
         log!("Searching for threads...");
+        synonyms_applied.track();
         if let Some(searcher) = searcher.read().as_deref() {
             searcher
                 .as_ref()
                 .unwrap()
                 .lock()
                 .unwrap()
-                .find_threads(search_query.get().clone())
-                .unwrap_or_default()
+                .find_thread_ids(search_query.get().clone())
+        } else {
+            Ok(Vec::new())
+        }
+    });
+
+    let query_error = Memo::new(move |_| found_thread_ids.with(|result| result.clone().err()));
+
+    // only the threads in view are summarized, scrolling doesn't search again
+    let result_page = Memo::new(move |_| {
+        let window = thread_window.get();
+        let query_words = query_words.get();
+        found_thread_ids.with(|result| {
+            let thread_ids: &[usize] = result.as_deref().unwrap_or_default();
+            let total = thread_ids.len();
+            let window = window.start.min(total)..window.end.min(total);
+            let threads = searcher
+                .read()
+                .as_deref()
+                .flatten()
+                .map(|searcher| {
+                    searcher
+                        .lock()
+                        .unwrap()
+                        .get_thread_results(&thread_ids[window.clone()], &query_words)
+                })
+                .unwrap_or_default();
+            ThreadPage {
+                threads,
+                offset: window.start,
+                total,
+            }
+        })
+    });
+
+    let similar_threads = Memo::new(move |_| {
+        let similar_to = similar_to.get()?;
+        searcher.read().as_deref().flatten().map(|searcher| {
+            searcher
+                .lock()
+                .unwrap()
+                .similar_threads(similar_to as usize)
+        })
+    });
+
+    let thread_page = Memo::new(move |_| match similar_threads.get() {
+        Some(threads) => ThreadPage {
+            offset: 0,
+            total: threads.len(),
+            threads,
+        },
+        None => result_page.get(),
+    });
+
+    let warnings = Memo::new(move |_| {
+        if let Some(searcher) = searcher.read().as_deref() {
+            searcher
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .get_query_warnings(search_query.get().clone())
         } else {
             Vec::new()
         }
     });
 
-    let message_border_ids = Memo::new(move |_| {
-        log!("Retrieving messages...");
-        let selected_thread_id = selected_thread_id.get();
-        if let Some(selected_thread_id) = selected_thread_id {
+    let suggestion = Memo::new(move |_| {
+        let search_query = search_query.get();
+        if search_query.is_empty()
+            || query_error.with(Option::is_some)
+            || result_page.with(|page| page.total > 0)
+        {
+            return None;
+        }
+        searcher
+            .read()
+            .as_deref()
+            .flatten()
+            .and_then(|searcher| searcher.lock().unwrap().suggest(search_query))
+    });
+
+    // the context view opens at the first message of the thread
+    let focused_message_id = Memo::new(move |_| {
+        let selected_thread_id = selected_thread_id.get()?;
+        searcher.read().as_deref().flatten().map(|searcher| {
+            let (min_id, _) = searcher
+                .lock()
+                .unwrap()
+                .get_thread_messages(selected_thread_id as usize);
+            min_id
+        })
+    });
+
+    let links = Memo::new(move |_| {
+        if let Some(selected_thread_id) = selected_thread_id.get() {
             if let Some(searcher) = searcher.read().as_deref() {
-                set_offset_after.set(0);
-                set_offset_before.set(0);
-                let (min_id, max_id) = searcher
+                return searcher
                     .as_ref()
                     .unwrap()
                     .lock()
                     .unwrap()
-                    .get_thread_messages(selected_thread_id as usize);
-                Some((min_id, max_id))
-            } else {
-                None
+                    .get_thread_links(selected_thread_id as usize);
             }
-        } else {
-            None
         }
+        Vec::new()
     });
 
-    let messages = Memo::new(move |_| {
-        if let Some((min_id, max_id)) = message_border_ids.get() {
+    let thread_tree = Memo::new(move |_| {
+        if let Some(selected_thread_id) = selected_thread_id.get() {
             if let Some(searcher) = searcher.read().as_deref() {
-                log!(
-                    "Retrieving messages..., min_id: {}, max_id: {}",
-                    min_id,
-                    max_id
-                );
                 let query_words = query_words.get();
-                let query_words = query_words
-                    .iter()
-                    .map(|word| word.as_str())
-                    .collect::<Vec<_>>();
-                searcher
+                return searcher
                     .as_ref()
                     .unwrap()
                     .lock()
                     .unwrap()
-                    .get_message_range(
-                        min_id.saturating_sub(offset_before.get()),
-                        max_id.saturating_add(offset_after.get()),
-                        &query_words,
-                    )
-            } else {
-                Vec::new()
+                    .get_thread_tree(selected_thread_id as usize, &query_words);
             }
-        } else {
-            Vec::new()
         }
+        Vec::new()
     });
 
     view! {
-        <SearchBar set_search_query=set_search_query />
-        <div class="grid grid-cols-2 gap-8 h-[calc(100vh-102px)]">
-            <div class="overflow-y-auto">
-                <ThreadList
-                    threads=result_threads
-                    set_selected_thread_id=set_selected_thread_id
-                />
+        <div class="mb-3 flex gap-2 text-sm">
+            <ViewModeButton label="Search" active=Signal::derive(move || !browse.get()) on_click=move |_| set_browse.set(false) />
+            <ViewModeButton label="Browse" active=Signal::derive(move || browse.get()) on_click=move |_| set_browse.set(true) />
+        </div>
+        <Show
+            when=move || !browse.get()
+            fallback=move || view! { <Browse searcher=searcher query_words=query_words /> }
+        >
+            <SearchBar
+                draft_query=draft_query
+                set_draft_query=set_draft_query
+                on_search=submit_query
+                completions=completions
+            />
+            <QueryErrorMessage search_query=search_query query_error=query_error />
+            <Suggestion suggestion=suggestion on_search=submit_query />
+            <QueryWarnings warnings=warnings />
+            <SynonymsEditor synonyms=synonyms_text set_synonyms=set_synonyms_text />
+            <SegmentationSettings segmentation=segmentation set_segmentation=set_segmentation />
+            <div class="grid grid-cols-[1fr_1fr_16rem] gap-8 h-[calc(100vh-102px)]">
+                <div class="flex flex-col min-h-0">
+                    <Show when=move || similar_to.get().is_some()>
+                        <div class="mb-3 flex justify-between items-center text-sm">
+                            <span>"Threads similar to the selected one"</span>
+                            <Button label="Back to results" on_click=move |_| set_similar_to.set(None) />
+                        </div>
+                    </Show>
+                    // a new list for every query, so that it starts scrolled to the top
+                    <div class="flex-1 min-h-0">
+                        {move || {
+                            search_query.track();
+                            similar_to.track();
+                            view! {
+                                <ThreadList
+                                    page=thread_page
+                                    set_window=set_thread_window
+                                    set_selected_thread_id=set_selected_thread_id
+                                />
+                            }
+                        }}
+                    </div>
+                </div>
+                <div class="flex flex-col min-h-0">
+                    <Show when=move || selected_thread_id.get().is_some()>
+                        <div class="mb-3 flex gap-2 text-sm">
+                            <ViewModeButton label="Replies" active=Signal::derive(move || !show_context.get()) on_click=move |_| set_show_context.set(false) />
+                            <ViewModeButton label="Context" active=Signal::derive(move || show_context.get()) on_click=move |_| set_show_context.set(true) />
+                            <div class="ml-auto">
+                                <ViewModeButton
+                                    label="More like this"
+                                    active=Signal::derive(move || similar_to.get().is_some() && similar_to.get() == selected_thread_id.get())
+                                    on_click=move |_| set_similar_to.set(selected_thread_id.get_untracked())
+                                />
+                            </div>
+                        </div>
+                    </Show>
+                    <div class="flex-1 min-h-0">
+                        <Show
+                            when=move || show_context.get()
+                            fallback=move || view! {
+                                <div class="h-full overflow-y-auto">
+                                    <ThreadTree trees=thread_tree />
+                                </div>
+                            }
+                        >
+                            <MessageList searcher=searcher focused_message_id=focused_message_id query_words=query_words />
+                        </Show>
+                    </div>
+                </div>
+                <div class="overflow-y-auto">
+                    <ThreadLinks links=links />
+                </div>
             </div>
-            <div class="overflow-y-auto">
-                <MessageList messages=messages set_offset_before=set_offset_before set_offset_after=set_offset_after />
+        </Show>
+    }
+}
+
+/// The whole chat in order, opened at the last message or at the first message
+/// of the chosen day.
+#[component]
+fn Browse(
+    searcher: LocalResource<Option<Arc<Mutex<Searcher>>>>,
+    query_words: Memo<QueryWords>,
+) -> impl IntoView {
+    let (date, set_date) = signal(String::new());
+
+    let focused_message_id = Memo::new(move |_| {
+        let date = date.get();
+        searcher.read().as_deref().flatten().and_then(|searcher| {
+            let searcher = searcher.lock().unwrap();
+            if date.is_empty() {
+                searcher.message_count().checked_sub(1)
+            } else {
+                searcher.find_message_by_date(&date)
+            }
+        })
+    });
+
+    view! {
+        <div class="flex flex-col h-[calc(100vh-102px)]">
+            <label class="mb-3 text-sm">
+                "Jump to "
+                <input
+                    type="date"
+                    class="bg-gray-700 px-2 border border-gray-300 rounded"
+                    prop:value=date
+                    on:change=move |ev| set_date.set(event_target_value(&ev))
+                />
+            </label>
+            <div class="flex-1 min-h-0">
+                <MessageList searcher=searcher focused_message_id=focused_message_id query_words=query_words />
             </div>
         </div>
     }
 }
 
+/// How many threads are fetched before the list knows its height.
+const THREAD_PAGE_SIZE: usize = 20;
+
+/// Height of a row of the thread list in pixels, all rows are the same height
+/// so that only the visible ones are rendered.
+const THREAD_ROW_HEIGHT: usize = 112;
+
 #[component]
 fn ThreadList(
-    threads: Memo<Vec<ThreadSearchResult>>,
+    page: Memo<ThreadPage>,
+    set_window: WriteSignal<Range<usize>>,
     set_selected_thread_id: WriteSignal<Option<u32>>,
 ) -> impl IntoView {
     view! {
-        <ul>
+        <VirtualList
+            total=Signal::derive(move || page.with(|page| page.total))
+            offset=Signal::derive(move || page.with(|page| page.offset))
+            row_height=THREAD_ROW_HEIGHT
+            set_window=set_window
+        >
             {move || {
-                threads.with(|threads| {
-                    threads.clone().into_iter().map(|thread| {
+                page.with(|page| {
+                    page.threads.clone().into_iter().map(|thread| {
                         let date = DateTime::from_timestamp(thread.date_unixtime as i64, 0).expect("Failed to parse date").format("%Y-%m").to_string();
+                        let snippet = thread.snippet.clone();
                         view! {
-                            <li class="p-2 hover:bg-gray-700 cursor-pointer" data-id={thread.thread_id} on:click=move |_| set_selected_thread_id.set(Some(thread.thread_id))>
+                            <li
+                                class="p-2 hover:bg-gray-700 cursor-pointer overflow-hidden"
+                                style=format!("height: {}px", THREAD_ROW_HEIGHT)
+                                data-id={thread.thread_id}
+                                on:click=move |_| set_selected_thread_id.set(Some(thread.thread_id))
+                            >
                                 <div class="flex justify-between items-center">
                                     <span class="truncate">{thread.title_text.clone()}</span>
                                     <span class="text-sm whitespace-nowrap">{date}</span>
                                 </div>
+                                {(!snippet.is_empty()).then(|| view! {
+                                    <div class="text-sm text-gray-300 line-clamp-2">
+                                        <MessageText text=snippet />
+                                    </div>
+                                })}
+                                <ThreadStats thread=thread.clone() />
                             </li>
                         }
                     }).collect::<Vec<_>>()
                 })
             }}
-        </ul>
+        </VirtualList>
     }
 }
 
+/// How many participants are named in the thread list, the rest are only counted.
+const SHOWN_PARTICIPANTS: usize = 3;
+
+#[component]
+fn ThreadStats(thread: ThreadSearchResult) -> impl IntoView {
+    let format_date = |date_unixtime: u32| {
+        DateTime::from_timestamp(date_unixtime as i64, 0)
+            .expect("Failed to parse date")
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    let first_date = format_date(thread.date_unixtime);
+    let last_date = format_date(thread.last_date_unixtime);
+    let time_span = if first_date == last_date {
+        first_date
+    } else {
+        format!("{} – {}", first_date, last_date)
+    };
+    let mut participants = thread
+        .participants
+        .iter()
+        .take(SHOWN_PARTICIPANTS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if thread.participants.len() > SHOWN_PARTICIPANTS {
+        participants.push_str(&format!(
+            " +{}",
+            thread.participants.len() - SHOWN_PARTICIPANTS
+        ));
+    }
+    let plural = |count: usize, word: &str| {
+        format!("{} {}{}", count, word, if count == 1 { "" } else { "s" })
+    };
+    view! {
+        <div class="flex flex-wrap gap-x-3 text-xs text-gray-400">
+            <span>{plural(thread.message_count, "message")}</span>
+            {(thread.matched_count > 0).then(|| view! {
+                <span class="text-teal-300">{thread.matched_count}" matched"</span>
+            })}
+            {(thread.reply_depth > 0).then(|| view! {
+                <span>{plural(thread.reply_depth, "reply level")}</span>
+            })}
+            <span>{time_span}</span>
+            <span class="truncate" title=thread.participants.join(", ")>{participants}</span>
+        </div>
+    }
+}
+
+/// How many messages are loaded around the focused message and then at a time
+/// when the context view is scrolled to either end.
+const CONTEXT_STEP: usize = 20;
+
+/// How close to an end of the context view, in pixels, more messages are loaded.
+const LOAD_THRESHOLD: i32 = 200;
+
+/// Where the context view scrolls once the loaded messages are rendered.
+#[derive(Debug, Clone, Copy)]
+enum ScrollAnchor {
+    /// The message comes into view
+    Message(usize),
+    /// The content keeps its distance from the bottom, as messages are added above
+    FromBottom(i32),
+}
+
+/// The chat around the focused message, more messages are loaded in both directions
+/// as it is scrolled.
 #[component]
 fn MessageList(
-    messages: Memo<Vec<MessageResult>>,
-    set_offset_before: WriteSignal<usize>,
-    set_offset_after: WriteSignal<usize>,
+    searcher: LocalResource<Option<Arc<Mutex<Searcher>>>>,
+    focused_message_id: Memo<Option<usize>>,
+    query_words: Memo<QueryWords>,
 ) -> impl IntoView {
+    let container: NodeRef<html::Div> = NodeRef::new();
+    // the first and the last loaded message
+    let (loaded, set_loaded) = signal(None::<(usize, usize)>);
+    let (messages, set_messages) = signal(Vec::new());
+    // part of the keys of the rows, so that a reload renders them anew with its highlights
+    let reloads = StoredValue::new(0usize);
+    let anchor = StoredValue::new(None::<ScrollAnchor>);
+
+    let message_count = Memo::new(move |_| {
+        searcher
+            .read()
+            .as_deref()
+            .flatten()
+            .map_or(0, |searcher| searcher.lock().unwrap().message_count())
+    });
+
+    let fetch = move |first: usize, last: usize| {
+        log!("Retrieving messages..., first: {}, last: {}", first, last);
+        searcher
+            .read_untracked()
+            .as_deref()
+            .flatten()
+            .map(|searcher| {
+                query_words.with_untracked(|query_words| {
+                    searcher
+                        .lock()
+                        .unwrap()
+                        .get_message_range(first, last, query_words)
+                })
+            })
+            .unwrap_or_default()
+    };
+
+    // everything is loaded anew only when the focus or the highlighted words change
+    Effect::new(move |_| {
+        query_words.track();
+        if let Some(message_id) = focused_message_id.get() {
+            let last = message_count.get().saturating_sub(1);
+            let first = message_id.saturating_sub(CONTEXT_STEP);
+            let last = message_id.saturating_add(CONTEXT_STEP).min(last);
+            reloads.update_value(|reloads| *reloads += 1);
+            set_messages.set(fetch(first, last));
+            set_loaded.set(Some((first, last)));
+            anchor.set_value(Some(ScrollAnchor::Message(message_id)));
+        } else {
+            set_messages.set(Vec::new());
+            set_loaded.set(None);
+        }
+    });
+
+    // loads the next messages when an end of the loaded ones is close to the view
+    let load_more = move |container: &web_sys::HtmlDivElement| {
+        let (first, last) = match loaded.get_untracked() {
+            Some(loaded) => loaded,
+            None => return,
+        };
+        let scroll_top = container.scroll_top();
+        let scroll_height = container.scroll_height();
+        if scroll_top < LOAD_THRESHOLD && first > 0 {
+            let new_first = first.saturating_sub(CONTEXT_STEP);
+            let previous = fetch(new_first, first - 1);
+            anchor.set_value(Some(ScrollAnchor::FromBottom(scroll_height - scroll_top)));
+            set_messages.update(|messages| {
+                messages.splice(0..0, previous);
+            });
+            set_loaded.set(Some((new_first, last)));
+        } else if scroll_top + container.client_height() > scroll_height - LOAD_THRESHOLD
+            && last + 1 < message_count.get_untracked()
+        {
+            let new_last = last + CONTEXT_STEP;
+            let next = fetch(last + 1, new_last);
+            set_messages.update(|messages| messages.extend(next));
+            set_loaded.set(Some((
+                first,
+                new_last.min(message_count.get_untracked() - 1),
+            )));
+        }
+    };
+
+    // runs after the new messages are rendered, whenever the loaded range changes even
+    // if no message was added, so that the anchor never blocks loading
+    Effect::new(move |_| {
+        loaded.track();
+        let container = match container.get() {
+            Some(container) => container,
+            None => return,
+        };
+        let mut scroll_anchor = None;
+        anchor.update_value(|anchor| scroll_anchor = anchor.take());
+        match scroll_anchor {
+            Some(ScrollAnchor::Message(message_id)) => {
+                let selector = format!("[data-id='{}']", message_id);
+                if let Ok(Some(element)) = container.query_selector(&selector) {
+                    element.scroll_into_view();
+                }
+            }
+            Some(ScrollAnchor::FromBottom(distance)) => {
+                container.set_scroll_top(container.scroll_height() - distance);
+            }
+            None => {}
+        }
+        // the loaded messages may not fill the view, then no scrolling would load more
+        load_more(&container);
+    });
+
+    let on_scroll = move |_| {
+        // the previous messages are not in place yet
+        if anchor.with_value(Option::is_some) {
+            return;
+        }
+        if let Some(container) = container.get_untracked() {
+            load_more(&container);
+        }
+    };
+
     view! {
-        <ul>
-        {move || {
-            messages.with(|messages| {
-                let btn_before = view! {
-                    <li class="mb-3"><Button on_click=move |_| *set_offset_before.write() += 5 /></li>
-                };
-                    let view_messages = messages.clone().into_iter().map(|message| {
-                        let reply_text = message.reply_to_text.clone().map(|text| {
+        <div node_ref=container class="h-full overflow-y-auto [overflow-anchor:none]" on:scroll=on_scroll>
+            <ul>
+                <For
+                    each=move || messages.get()
+                    key=move |message| (reloads.get_value(), message.message_id)
+                    children=move |message| {
+                        let reply_text = message.reply_to_text.map(|text| {
                             view! {
                                 <div class="truncate bg-gray-900/40 rounded p-1">
                                     {text}
                                 </div>
                             }
                         });
-                        let message_text = message.text.clone();
-                        let highlighted_text = message_text.into_iter().map(|text| {
-                            let text = text.clone();
-                            match text {
-                                Text::Highlight(text) => {
-                                    Either::Left(view! {
-                                        <span class="bg-teal-400/50 rounded p-0.5">{text.clone()}</span>
-                                    })
-                                },
-                                Text::Plain(text) => {
-                                    Either::Right(view! {
-                                        <span>{text.clone()}</span>
-                                    })
-                                }
-                            }
-                        }).collect::<Vec<_>>();
                         view! {
-                            <li class="p-2 mb-3 bg-sky-400/25 border-sky-700/40 border rounded">
-                                {reply_text.clone()}
-                                {highlighted_text}
+                            <li class="p-2 mb-3 bg-sky-400/25 border-sky-700/40 border rounded" data-id=message.message_id>
+                                {reply_text}
+                                <MessageText text=message.text />
                             </li>
                         }
-                    }).collect::<Vec<_>>();
+                    }
+                />
+            </ul>
+        </div>
+    }
+}
 
-                    let btn_after = view! {
-                        <li><Button on_click=move |_| *set_offset_after.write() += 5 /></li>
-                    };
+#[component]
+fn MessageText(text: Vec<Text>) -> impl IntoView {
+    text.into_iter()
+        .map(|text| match text {
+            Text::Highlight(text) => Either::Left(view! {
+                <span class="bg-teal-400/50 rounded p-0.5">{text}</span>
+            }),
+            Text::Plain(text) => Either::Right(view! { <span>{text}</span> }),
+        })
+        .collect::<Vec<_>>()
+}
 
-                    (btn_before, view_messages, btn_after)
-                })
-            }}
+/// The thread as nested replies, each branch can be collapsed.
+#[component]
+fn ThreadTree(trees: Memo<Vec<MessageTree>>) -> impl IntoView {
+    view! {
+        <ul>
+            {move || trees.get().into_iter().map(message_tree_view).collect::<Vec<_>>()}
         </ul>
     }
 }
 
+// `get_thread_tree` caps the nesting depth, so the recursion stays shallow
+fn message_tree_view(tree: MessageTree) -> AnyView {
+    let (collapsed, set_collapsed) = signal(false);
+    let replies_count = tree.replies.len();
+    let toggle = (replies_count > 0).then(|| {
+        view! {
+            <button
+                class="mt-1 text-xs text-sky-300 hover:text-sky-200"
+                on:click=move |_| set_collapsed.update(|collapsed| *collapsed = !*collapsed)
+            >
+                {move || if collapsed.get() { "▸ " } else { "▾ " }}
+                {replies_count}
+                {if replies_count == 1 { " reply" } else { " replies" }}
+            </button>
+        }
+    });
+    let replies = tree.replies;
+    view! {
+        <li class="mb-3">
+            <div class="p-2 bg-sky-400/25 border-sky-700/40 border rounded">
+                <MessageText text=tree.message.text />
+            </div>
+            {toggle}
+            <Show when=move || !collapsed.get()>
+                <ul class="mt-3 ml-4 pl-3 border-l border-sky-700/40">
+                    {replies.clone().into_iter().map(message_tree_view).collect::<Vec<_>>()}
+                </ul>
+            </Show>
+        </li>
+    }
+    .into_any()
+}
+
+#[component]
+fn Button(label: &'static str, on_click: impl FnMut(MouseEvent) + 'static) -> impl IntoView {
+    view! {
+        <button
+            on:click=on_click
+            class="px-3 py-1 border border-sky-600 rounded hover:bg-sky-400/25 transition-colors"
+        >
+            {label}
+        </button>
+    }
+}
+
 #[component]
-fn SearchBar(set_search_query: WriteSignal<String>) -> impl IntoView {
+fn ViewModeButton(
+    label: &'static str,
+    active: Signal<bool>,
+    on_click: impl FnMut(MouseEvent) + 'static,
+) -> impl IntoView {
+    view! {
+        <button
+            on:click=on_click
+            class="px-3 py-1 border border-sky-600 rounded transition-colors"
+            class=("bg-sky-400/50", move || active.get())
+            class=("hover:bg-sky-400/25", move || !active.get())
+        >
+            {label}
+        </button>
+    }
+}
+
+#[component]
+fn ThreadLinks(links: Memo<Vec<LinkResult>>) -> impl IntoView {
+    move || {
+        links.with(|links| {
+            if links.is_empty() {
+                return None;
+            }
+            let items = links
+                .iter()
+                .map(|link| {
+                    view! {
+                        <li class="mb-2">
+                            <div class="text-xs text-gray-400">{link.domain.clone()}</div>
+                            <a
+                                class="block truncate text-sky-400 hover:text-sky-300"
+                                href=link.url.clone()
+                                target="_blank"
+                                rel="noopener noreferrer"
+                                title=link.url.clone()
+                            >
+                                {link.url.clone()}
+                            </a>
+                        </li>
+                    }
+                })
+                .collect::<Vec<_>>();
+            Some(view! {
+                <h2 class="mb-2 font-bold">"Links in this thread"</h2>
+                <ul class="text-sm">{items}</ul>
+            })
+        })
+    }
+}
+
+/// The query input with completions of the last word, chosen with the arrow keys and
+/// Tab or Enter, or with the mouse.
+#[component]
+fn SearchBar(
+    draft_query: ReadSignal<String>,
+    set_draft_query: WriteSignal<String>,
+    on_search: Callback<String>,
+    completions: Memo<Vec<Completion>>,
+) -> impl IntoView {
     let input_element: NodeRef<html::Input> = NodeRef::new();
+    let (selected_completion, set_selected_completion) = signal(None::<usize>);
+    let (show_completions, set_show_completions) = signal(false);
+
+    let complete = move |query: String| {
+        set_draft_query.set(query);
+        set_selected_completion.set(None);
+        if let Some(input) = input_element.get() {
+            let _ = input.focus();
+        }
+    };
+
+    let on_keydown = move |e: KeyboardEvent| {
+        let count = completions.with(Vec::len);
+        if !show_completions.get() || count == 0 {
+            return;
+        }
+        match e.key().as_str() {
+            "ArrowDown" => {
+                e.prevent_default();
+                set_selected_completion
+                    .update(|selected| *selected = Some(selected.map_or(0, |i| (i + 1) % count)));
+            }
+            "ArrowUp" => {
+                e.prevent_default();
+                set_selected_completion.update(|selected| {
+                    *selected = Some(selected.map_or(count - 1, |i| (i + count - 1) % count))
+                });
+            }
+            "Enter" | "Tab" => {
+                let selected = selected_completion
+                    .get()
+                    .and_then(|i| completions.with(|completions| completions.get(i).cloned()));
+                if let Some(completion) = selected {
+                    e.prevent_default();
+                    complete(completion.query);
+                }
+            }
+            "Escape" => set_show_completions.set(false),
+            _ => {}
+        }
+    };
+
     view! {
             <form on:submit= move |e| {
                 e.prevent_default();
-                let value = input_element.get().expect("input element").value();
-                set_search_query.set(value);
+                set_show_completions.set(false);
+                on_search.run(draft_query.get());
             }>
                 <div class="mb-6 flex">
-                    <input type="text" placeholder="search" class="w-full bg-gray-700 p-2 border border-gray-300 rounded" node_ref=input_element />
+                    <div class="relative w-full">
+                        <input
+                            type="text"
+                            placeholder="search"
+                            class="w-full bg-gray-700 p-2 border border-gray-300 rounded"
+                            prop:value=move || draft_query.get()
+                            node_ref=input_element
+                            on:input=move |e| {
+                                set_draft_query.set(event_target_value(&e));
+                                set_selected_completion.set(None);
+                                set_show_completions.set(true);
+                            }
+                            on:keydown=on_keydown
+                            on:blur=move |_| set_show_completions.set(false)
+                        />
+                        <Show when=move || show_completions.get() && completions.with(|completions| !completions.is_empty())>
+                            <ul class="absolute z-10 w-full mt-1 bg-gray-800 border border-gray-600 rounded shadow-lg">
+                                {move || {
+                                    completions.get().into_iter().enumerate().map(|(i, completion)| {
+                                        let query = completion.query.clone();
+                                        view! {
+                                            <li
+                                                class="px-2 py-1 cursor-pointer hover:bg-gray-700"
+                                                class=("bg-gray-700", move || selected_completion.get() == Some(i))
+                                                // mousedown comes before the input loses focus and hides the list
+                                                on:mousedown=move |e| {
+                                                    e.prevent_default();
+                                                    complete(query.clone());
+                                                }
+                                            >
+                                                {completion.label}
+                                            </li>
+                                        }
+                                    }).collect::<Vec<_>>()
+                                }}
+                            </ul>
+                        </Show>
+                    </div>
                     <input type="submit" value="Search" class="ml-2 p-2 border bg-sky-400/25 border-sky-600 rounded hover:bg-sky-400/50 transition-colors cursor-pointer" />
                 </div>
             </form>
     }
 }
+
 #[component]
-fn Button(on_click: impl FnMut(MouseEvent) + 'static) -> impl IntoView {
+fn Suggestion(suggestion: Memo<Option<String>>, on_search: Callback<String>) -> impl IntoView {
+    move || {
+        suggestion.get().map(|suggestion| {
+            let query = suggestion.clone();
+            view! {
+                <p class="-mt-4 mb-4">
+                    "Did you mean "
+                    <a
+                        class="underline cursor-pointer text-sky-400 hover:text-sky-300"
+                        on:click=move |_| on_search.run(query.clone())
+                    >
+                        {suggestion}
+                    </a>
+                    "?"
+                </p>
+            }
+        })
+    }
+}
+
+/// Repeats the query with the offending part underlined, followed by the error itself.
+#[component]
+fn QueryErrorMessage(
+    search_query: ReadSignal<String>,
+    query_error: Memo<Option<QueryError>>,
+) -> impl IntoView {
+    move || {
+        query_error.get().map(|error| {
+            let query: Vec<char> = search_query.get().chars().collect();
+            let underlined_query = error.span.clone().map(|span| {
+                let before: String = query[..span.start].iter().collect();
+                let mut offending: String = query[span.clone()].iter().collect();
+                if offending.is_empty() {
+                    // the query ended too early, underline the place where something is missing
+                    offending.push(' ');
+                }
+                let after: String = query[span.end..].iter().collect();
+                view! {
+                    <pre class="font-mono">
+                        {before}
+                        <span class="underline decoration-wavy decoration-red-500">{offending}</span>
+                        {after}
+                    </pre>
+                }
+            });
+            view! {
+                <div class="-mt-4 mb-4 text-sm text-red-400">
+                    {underlined_query}
+                    <p class="whitespace-pre-wrap">{error.to_string()}</p>
+                </div>
+            }
+        })
+    }
+}
+
+#[component]
+fn QueryWarnings(warnings: Memo<Vec<String>>) -> impl IntoView {
     view! {
-        <button on:click=on_click class="w-full p-2 bg-sky-400/25 border-sky-600 border rounded hover:bg-sky-400/50 transition-colors">
-            Load More
-        </button>
+        <ul class="-mt-4 mb-4 text-sm text-amber-400">
+            {move || {
+                warnings
+                    .get()
+                    .into_iter()
+                    .map(|warning| view! { <li>{warning}</li> })
+                    .collect::<Vec<_>>()
+            }}
+        </ul>
     }
 }
@@ -0,0 +1,106 @@
+use crate::analysis::Segmentation;
+use leptos::prelude::*;
+
+const GAP_STORAGE_KEY: &str = "segmentation_max_gap_minutes";
+const ALTERNATION_STORAGE_KEY: &str = "segmentation_sender_alternation";
+const SPLIT_STORAGE_KEY: &str = "segmentation_split_threads";
+
+/// The gap offered when the grouping by time is switched on.
+const DEFAULT_MAX_GAP_MINUTES: u32 = 30;
+
+pub fn load_segmentation() -> Segmentation {
+    let storage = window().local_storage().ok().flatten();
+    let get_item = |key| {
+        storage
+            .as_ref()
+            .and_then(|storage| storage.get_item(key).ok().flatten())
+    };
+    Segmentation {
+        max_gap_minutes: get_item(GAP_STORAGE_KEY).and_then(|gap| gap.parse().ok()),
+        sender_alternation: get_item(ALTERNATION_STORAGE_KEY).as_deref() == Some("true"),
+        split_threads: get_item(SPLIT_STORAGE_KEY).as_deref() == Some("true"),
+    }
+}
+
+fn save_segmentation(segmentation: Segmentation) {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        match segmentation.max_gap_minutes {
+            Some(gap) => _ = storage.set_item(GAP_STORAGE_KEY, &gap.to_string()),
+            None => _ = storage.remove_item(GAP_STORAGE_KEY),
+        }
+        _ = storage.set_item(
+            ALTERNATION_STORAGE_KEY,
+            &segmentation.sender_alternation.to_string(),
+        );
+        _ = storage.set_item(SPLIT_STORAGE_KEY, &segmentation.split_threads.to_string());
+    }
+}
+
+/// Chooses how messages that are not replies are grouped, the chat is indexed again
+/// after every change.
+#[component]
+pub fn SegmentationSettings(
+    segmentation: ReadSignal<Segmentation>,
+    set_segmentation: WriteSignal<Segmentation>,
+) -> impl IntoView {
+    let update = move |segmentation: Segmentation| {
+        save_segmentation(segmentation);
+        set_segmentation.set(segmentation);
+    };
+    view! {
+        <details class="mb-6">
+            <summary class="cursor-pointer">Conversations</summary>
+            <p class="text-sm my-2">
+                Besides the replies, messages sent close to each other can be grouped into one conversation.
+            </p>
+            <label class="block mb-2">
+                <input
+                    type="checkbox"
+                    class="mr-2"
+                    prop:checked=move || segmentation.get().max_gap_minutes.is_some()
+                    on:change=move |ev| {
+                        let max_gap_minutes = event_target_checked(&ev).then_some(DEFAULT_MAX_GAP_MINUTES);
+                        update(Segmentation { max_gap_minutes, ..segmentation.get() });
+                    }
+                />
+                "Group messages at most "
+                <input
+                    type="number"
+                    min="1"
+                    class="w-20 bg-gray-700 px-2 border border-gray-300 rounded"
+                    prop:value=move || segmentation.get().max_gap_minutes.unwrap_or(DEFAULT_MAX_GAP_MINUTES).to_string()
+                    prop:disabled=move || segmentation.get().max_gap_minutes.is_none()
+                    on:change=move |ev| {
+                        if let Ok(gap) = event_target_value(&ev).parse::<u32>() {
+                            update(Segmentation { max_gap_minutes: Some(gap.max(1)), ..segmentation.get() });
+                        }
+                    }
+                />
+                " minutes apart"
+            </label>
+            <label class="block mb-2">
+                <input
+                    type="checkbox"
+                    class="mr-2"
+                    prop:checked=move || segmentation.get().sender_alternation
+                    prop:disabled=move || segmentation.get().max_gap_minutes.is_none()
+                    on:change=move |ev| {
+                        update(Segmentation { sender_alternation: event_target_checked(&ev), ..segmentation.get() });
+                    }
+                />
+                "Only when the sender changes, so that a new post after a pause starts a new conversation"
+            </label>
+            <label class="block">
+                <input
+                    type="checkbox"
+                    class="mr-2"
+                    prop:checked=move || segmentation.get().split_threads
+                    on:change=move |ev| {
+                        update(Segmentation { split_threads: event_target_checked(&ev), ..segmentation.get() });
+                    }
+                />
+                "Split huge threads: a reply to a week-old message, or to a message hours old in a thread of hundreds, starts a sub-thread"
+            </label>
+        </details>
+    }
+}
@@ -0,0 +1,43 @@
+use leptos::prelude::*;
+
+const STORAGE_KEY: &str = "synonyms";
+
+pub fn load_synonyms() -> String {
+    window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+        .unwrap_or_default()
+}
+
+fn save_synonyms(text: &str) {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        _ = storage.set_item(STORAGE_KEY, text);
+    }
+}
+
+#[component]
+pub fn SynonymsEditor(
+    synonyms: ReadSignal<String>,
+    set_synonyms: WriteSignal<String>,
+) -> impl IntoView {
+    view! {
+        <details class="mb-6">
+            <summary class="cursor-pointer">Synonyms</summary>
+            <p class="text-sm my-2">
+                One group per line, words separated by <code class="p-1">=</code>, e.g. <code class="p-1">прод = продакшн = prod</code>.
+            </p>
+            <textarea
+                rows="4"
+                class="w-full bg-gray-700 p-2 border border-gray-300 rounded"
+                prop:value=move || synonyms.get()
+                on:change=move |ev| {
+                    let text = event_target_value(&ev);
+                    save_synonyms(&text);
+                    set_synonyms.set(text);
+                }
+            />
+        </details>
+    }
+}
@@ -0,0 +1,60 @@
+use leptos::html;
+use leptos::prelude::*;
+use std::ops::Range;
+
+/// Rows rendered above and below the visible ones, so that fast scrolling doesn't show gaps.
+const OVERSCAN_ROWS: usize = 5;
+
+/// The rows to render when `height` pixels of the list are shown after scrolling
+/// by `scroll_top` pixels.
+fn visible_rows(scroll_top: usize, height: usize, row_height: usize) -> Range<usize> {
+    let first = scroll_top / row_height;
+    let last = (scroll_top + height).div_ceil(row_height);
+    first.saturating_sub(OVERSCAN_ROWS)..last + OVERSCAN_ROWS
+}
+
+/// A scrollable list of `total` rows, each `row_height` pixels high, that renders only
+/// the rows in view. The rows it needs are reported through `set_window`, `children` are
+/// the rows that are loaded, starting from row `offset`.
+#[component]
+pub fn VirtualList(
+    total: Signal<usize>,
+    offset: Signal<usize>,
+    row_height: usize,
+    set_window: WriteSignal<Range<usize>>,
+    children: Children,
+) -> impl IntoView {
+    let container: NodeRef<html::Div> = NodeRef::new();
+
+    let update_window = move || {
+        if let Some(container) = container.get_untracked() {
+            let rows = visible_rows(
+                container.scroll_top().max(0) as usize,
+                container.client_height().max(0) as usize,
+                row_height,
+            );
+            set_window.maybe_update(|window| {
+                let changed = *window != rows;
+                *window = rows;
+                changed
+            });
+        }
+    };
+
+    // the height of the list is only known once it is mounted
+    Effect::new(move |_| {
+        if container.get().is_some() {
+            update_window();
+        }
+    });
+
+    view! {
+        <div node_ref=container class="h-full overflow-y-auto" on:scroll=move |_| update_window()>
+            <div class="relative" style=move || format!("height: {}px", total.get() * row_height)>
+                <ul class="absolute inset-x-0" style=move || format!("top: {}px", offset.get() * row_height)>
+                    {children()}
+                </ul>
+            </div>
+        </div>
+    }
+}
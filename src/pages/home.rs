@@ -2,24 +2,43 @@ use leptos::either::Either;
 use leptos::logging::log;
 use leptos::prelude::*;
 
-use crate::analysis::{Lemmatizer, Searcher};
+use crate::analysis::{Lemmatizer, Searcher, Tokenizer};
 use crate::components::file_input::FileInput;
 use crate::components::search::Search;
+use crate::components::segmentation::load_segmentation;
+use chrono::{Local, NaiveDateTime, TimeZone};
 use std::sync::{Arc, Mutex};
 
+/// How far the local time of the browser is ahead of UTC at the given local time, in
+/// seconds, as the dates picked in the browser are local.
+fn local_utc_offset(local: NaiveDateTime) -> i32 {
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map_or(0, |time| time.offset().local_minus_utc())
+}
+
 #[component]
 pub fn Home() -> impl IntoView {
     let (messages_json, set_messages_json) = signal(None::<String>);
+    let (segmentation, set_segmentation) = signal(load_segmentation());
     let lemmatizer = Box::leak(Box::new(Lemmatizer::new()));
     let lemmatizer = &*lemmatizer;
 
     let searcher = LocalResource::new(move || {
         let messages_json = messages_json.get().clone();
+        let segmentation = segmentation.get();
         async move {
             log!("Initializing searcher...");
-            Some(Arc::new(Mutex::new(
-                Searcher::new(lemmatizer, messages_json?).ok()?,
-            )))
+            let mut searcher = Searcher::new(
+                lemmatizer,
+                Tokenizer::default(),
+                segmentation,
+                messages_json?,
+            )
+            .ok()?;
+            searcher.set_utc_offset(local_utc_offset);
+            Some(Arc::new(Mutex::new(searcher)))
         }
     });
 
@@ -35,7 +54,11 @@ pub fn Home() -> impl IntoView {
                         <Show when=move || {
                             searcher.read().as_deref().flatten().cloned().is_some()
                         }>
-                            <Search searcher=searcher />
+                            <Search
+                                searcher=searcher
+                                segmentation=segmentation
+                                set_segmentation=set_segmentation
+                            />
                         </Show>
                     })
                 }